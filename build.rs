@@ -0,0 +1,31 @@
+extern crate wayland_scanner;
+
+use std::env;
+use std::path::Path;
+
+use wayland_scanner::{Side, generate_code, generate_interfaces};
+
+fn main() {
+    // the protocols are only used by the wayland backend
+    let target_os = env::var("CARGO_CFG_TARGET_OS").unwrap_or_default();
+    if !["linux", "dragonfly", "freebsd", "openbsd"].contains(&&target_os[..]) {
+        return;
+    }
+
+    let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = env::var("OUT_DIR").unwrap();
+    let out_dir = Path::new(&out_dir);
+
+    for &(file, name) in &[("xdg-shell.xml", "xdg_shell"),
+                           ("xdg-decoration-unstable-v1.xml", "xdg_decoration"),
+                           ("pointer-constraints-unstable-v1.xml", "pointer_constraints"),
+                           ("relative-pointer-unstable-v1.xml", "relative_pointer"),
+                           ("xdg-toplevel-icon-v1.xml", "xdg_toplevel_icon"),
+                           ("primary-selection-unstable-v1.xml", "primary_selection")]
+    {
+        let protocol = Path::new(&manifest_dir).join("protocols").join(file);
+        println!("cargo:rerun-if-changed={}", protocol.display());
+        generate_code(&protocol, out_dir.join(format!("{}_api.rs", name)), Side::Client);
+        generate_interfaces(&protocol, out_dir.join(format!("{}_interfaces.rs", name)));
+    }
+}
@@ -11,7 +11,7 @@ fn main() {
 
     for event in window.wait_events() {
         match event {
-            Event::KeyboardInput(ElementState::Pressed, _, _) => {
+            Event::KeyboardInput(ElementState::Pressed, _, _, _) => {
                 println!("Setting cursor to \"{:?}\"", cursors[cursor_idx]);
                 window.set_cursor(cursors[cursor_idx]);
                 if cursor_idx < cursors.len() - 1 {
@@ -33,7 +33,7 @@ fn main() {
 
         match event {
             winit::Event::Closed => break,
-            winit::Event::KeyboardInput(_, _, Some(winit::VirtualKeyCode::Escape)) => break,
+            winit::Event::KeyboardInput(_, _, Some(winit::VirtualKeyCode::Escape), _) => break,
             _ => ()
         }
     }
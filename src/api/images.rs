@@ -0,0 +1,86 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+//! Conversion and caching of the images of custom cursors and window icons.
+
+use CustomCursor;
+
+/// Number of custom cursors kept by a cache, so that switching between them is cheap.
+const MAX_CACHED_CURSORS: usize = 16;
+
+/// Returns RGBA pixels as `0xAARRGGBB` values with premultiplied alpha, which is the format of
+/// both Xcursor images and `wl_shm` buffers.
+pub fn premultiplied_argb(rgba: &[u8], width: u32, height: u32) -> Vec<u32> {
+    let len = width as usize * height as usize * 4;
+    rgba[.. len].chunks(4).map(|pixel| {
+        let alpha = pixel[3] as u32;
+        let premultiply = |c: u8| (c as u32 * alpha + 127) / 255;
+        alpha << 24 | premultiply(pixel[0]) << 16 | premultiply(pixel[1]) << 8 | premultiply(pixel[2])
+    }).collect()
+}
+
+/// The native cursors made from the last custom cursors used, the most recent last.
+pub struct CursorCache<T> {
+    entries: Vec<(CustomCursor, T)>,
+}
+
+impl<T> CursorCache<T> {
+    pub fn new() -> CursorCache<T> {
+        CursorCache {
+            entries: Vec::new(),
+        }
+    }
+
+    /// Returns the native cursor made from `cursor`, created with `create` unless it is cached.
+    ///
+    /// When the cache is full, the least recently used cursor is removed and given to `free`.
+    pub fn get<C, F>(&mut self, cursor: &CustomCursor, create: C, free: F) -> &T
+        where C: FnOnce() -> T, F: FnOnce(T)
+    {
+        match self.entries.iter().position(|&(ref c, _)| c == cursor) {
+            Some(index) => {
+                let entry = self.entries.remove(index);
+                self.entries.push(entry);
+            },
+            None => {
+                if self.entries.len() >= MAX_CACHED_CURSORS {
+                    free(self.entries.remove(0).1);
+                }
+                let native = create();
+                self.entries.push((cursor.clone(), native));
+            },
+        }
+        &self.entries.last().unwrap().1
+    }
+
+    /// Removes all the cursors, giving them to `free`.
+    pub fn clear<F>(&mut self, mut free: F) where F: FnMut(T) {
+        for (_, native) in self.entries.drain(..) {
+            free(native);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::premultiplied_argb;
+
+    #[test]
+    fn opaque_and_transparent_pixels() {
+        // opaque colors are kept, and transparent ones become black
+        assert_eq!(premultiplied_argb(&[0x12, 0x34, 0x56, 0xff, 0xff, 0xff, 0xff, 0x00], 2, 1),
+                   vec![0xff123456, 0x00000000]);
+    }
+
+    #[test]
+    fn premultiplication_rounds_to_nearest() {
+        // 2 * 191 / 255 is 1.498, 2 * 192 / 255 is 1.506 and 1 * 192 / 255 is 0.753
+        assert_eq!(premultiplied_argb(&[2, 2, 1, 191, 2, 2, 1, 192], 2, 1),
+                   vec![0xbf010101, 0xc0020201]);
+        assert_eq!(premultiplied_argb(&[255, 128, 0, 128], 1, 1), vec![0x80804000]);
+    }
+
+    #[test]
+    fn extra_pixels_are_ignored() {
+        assert_eq!(premultiplied_argb(&[1, 2, 3, 0xff, 4, 5, 6, 0xff], 1, 1), vec![0xff010203]);
+    }
+}
@@ -72,7 +72,8 @@ use libc;
 use objc::runtime::{Class, BOOL, YES, NO };
 
 use native_monitor::NativeMonitorId;
-use { Api, PixelFormat, CreationError, GlContext, CursorState, MouseCursor, Event };
+use { Api, PixelFormat, CreationError, GlContext, CursorState, CustomCursor, MouseCursor, Event };
+use { Selection, ClipboardData, Icon, RawHandle, Rect };
 use { PixelFormatRequirements, GlAttributes, WindowAttributes, ContextError };
 use CreationError::OsError;
 
@@ -282,6 +283,78 @@ impl Window {
     pub fn set_title(&self, _: &str) {
     }
 
+    #[inline]
+    pub fn set_fullscreen(&self, _: Option<MonitorId>) {
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_resizable(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_resize_increments(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, _: Selection, _: Vec<ClipboardData>) {
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, _: Selection, _: &str) -> Option<Vec<u8>> {
+        None
+    }
+
+    #[inline]
+    pub fn get_clipboard_mime_types(&self, _: Selection) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn request_redraw(&self) {
+    }
+
+    #[inline]
+    pub fn present_pixels(&self, _: &[u8], _: u32, _: u32, _: PixelFormat, _: Option<&[Rect]>) {
+    }
+
+    #[inline]
+    pub fn start_drag(&self, _: Vec<ClipboardData>) {
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        false
+    }
+
     #[inline]
     pub fn show(&self) {
     }
@@ -327,6 +400,15 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn raw_handle(&self) -> Option<RawHandle> {
+        let state = unsafe { &*self.delegate_state };
+        Some(RawHandle::UiKit {
+            ui_view: state.view as *mut _,
+            ui_window: state.window as *mut _,
+        })
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         unimplemented!();
@@ -350,6 +432,19 @@ impl Window {
     pub fn set_cursor(&self, _: MouseCursor) {
     }
 
+    #[inline]
+    pub fn set_window_icon(&self, _: &[Icon]) -> Result<(), CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn request_user_attention(&self) {
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, _: &CustomCursor) {
+    }
+
     #[inline]
     pub fn set_cursor_state(&self, _: CursorState) -> Result<(), String> {
         Ok(())
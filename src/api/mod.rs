@@ -1,6 +1,8 @@
 // TODO: remove this module altogether and move all implementations to `platform`
 
 pub mod dlopen;
+pub mod images;
+pub mod uri_list;
 pub mod wayland;
 pub mod x11;
 pub mod ios;
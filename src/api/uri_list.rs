@@ -0,0 +1,98 @@
+#![cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "openbsd"))]
+
+//! Parsing of the `text/uri-list` format used to drag and drop files, see RFC 2483.
+
+use std::ffi::OsString;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+
+use libc;
+
+/// Extracts the local paths from a `text/uri-list`. Other URIs are ignored.
+pub fn parse_uri_list(data: &[u8]) -> Vec<PathBuf> {
+    let hostname = hostname();
+    data.split(|&b| b == b'\n')
+        .map(|line| if line.last() == Some(&b'\r') { &line[.. line.len() - 1] } else { line })
+        .filter(|line| !line.is_empty() && line[0] != b'#')
+        .filter(|line| line.starts_with(b"file://"))
+        .filter_map(|line| {
+            let path = &line[b"file://".len() ..];
+            path.iter().position(|&b| b == b'/').map(|start| (&path[.. start], &path[start ..]))
+        })
+        // the host name is usually empty, the files of other machines can't be opened
+        .filter(|&(host, _)| {
+            host.is_empty() || host == b"localhost" || hostname.as_ref().map_or(false, |h| &h[..] == host)
+        })
+        .map(|(_, path)| PathBuf::from(OsString::from_vec(percent_decode(path))))
+        .collect()
+}
+
+// The name of this machine, which some clients put in the URIs of local files.
+fn hostname() -> Option<Vec<u8>> {
+    let mut hostname = [0u8; 256];
+    if unsafe { libc::gethostname(hostname.as_mut_ptr() as *mut libc::c_char, hostname.len()) } != 0 {
+        return None;
+    }
+    let len = hostname.iter().position(|&c| c == 0).unwrap_or(hostname.len());
+    Some(hostname[.. len].to_vec())
+}
+
+/// Replaces the `%XX` escapes by the bytes they stand for.
+fn percent_decode(input: &[u8]) -> Vec<u8> {
+    fn hex_value(b: u8) -> Option<u8> {
+        match b {
+            b'0' ..= b'9' => Some(b - b'0'),
+            b'a' ..= b'f' => Some(b - b'a' + 10),
+            b'A' ..= b'F' => Some(b - b'A' + 10),
+            _ => None,
+        }
+    }
+
+    let mut output = Vec::with_capacity(input.len());
+    let mut i = 0;
+    while i < input.len() {
+        if input[i] == b'%' && i + 2 < input.len() {
+            if let (Some(high), Some(low)) = (hex_value(input[i + 1]), hex_value(input[i + 2])) {
+                output.push(high << 4 | low);
+                i += 3;
+                continue;
+            }
+        }
+        output.push(input[i]);
+        i += 1;
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::{hostname, parse_uri_list};
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!(parse_uri_list(b"file:///tmp/a%20b%2fc%C3%A9"), vec![PathBuf::from("/tmp/a b/cé")]);
+        // invalid or truncated escapes are kept as they are
+        assert_eq!(parse_uri_list(b"file:///tmp/%zz%4"), vec![PathBuf::from("/tmp/%zz%4")]);
+    }
+
+    #[test]
+    fn line_endings_and_comments() {
+        let list = b"# a comment\r\nfile:///a\r\n\r\nfile:///b\nhttp://example.com/c\r\n#file:///d\r\n";
+        assert_eq!(parse_uri_list(list), vec![PathBuf::from("/a"), PathBuf::from("/b")]);
+    }
+
+    #[test]
+    fn host_names() {
+        assert_eq!(parse_uri_list(b"file://localhost/a"), vec![PathBuf::from("/a")]);
+        assert_eq!(parse_uri_list(b"file://other.example.com/a\r\nfile:/b\r\nfile://c"), Vec::<PathBuf>::new());
+
+        if let Some(hostname) = hostname() {
+            let mut list = b"file://".to_vec();
+            list.extend_from_slice(&hostname);
+            list.extend_from_slice(b"/a");
+            assert_eq!(parse_uri_list(&list), vec![PathBuf::from("/a")]);
+        }
+    }
+}
@@ -1,16 +1,52 @@
-use {Event, ElementState, MouseButton, MouseScrollDelta, TouchPhase};
+use {Event, ElementState, MouseButton, MouseCursor, CustomCursor, Icon, MouseScrollDelta, Touch, TouchPhase, ClipboardData,
+     DragAction, Selection};
 
 use std::collections::VecDeque;
-use std::sync::{Arc, Mutex};
+use std::fs::File;
+use std::io::{self, Read};
+use std::mem::{self, ManuallyDrop};
+use std::os::raw::c_void;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::atomic::{AtomicBool, AtomicPtr, Ordering};
+use std::time::{Duration, Instant};
 
-use wayland_client::{EnvHandler, default_connect, EventQueue, EventQueueHandle, Init, Proxy};
+use libc;
+
+use wayland_client::{EnvHandler, default_connect, EventQueue, EventQueueHandle, Handler, Init, Proxy};
 use wayland_client::protocol::{wl_compositor, wl_seat, wl_shell, wl_shm, wl_subcompositor,
                                wl_display, wl_registry, wl_output, wl_surface, wl_pointer,
-                               wl_keyboard};
+                               wl_keyboard, wl_data_device_manager, wl_data_device,
+                               wl_data_offer, wl_data_source, wl_shm_pool, wl_subsurface, wl_touch};
+use wayland_client::sys::{wl_argument, wl_proxy, WAYLAND_CLIENT_HANDLE};
 
 use super::wayland_window;
 use super::wayland_kbd::MappedKeyboard;
 use super::keyboard::KbdHandler;
+use super::cursor::{PointerCursor, WindowCursor};
+use super::frame::Frame;
+use super::xdg_shell::{xdg_wm_base, xdg_surface, xdg_toplevel};
+use super::xdg_decoration::{zxdg_decoration_manager_v1, zxdg_toplevel_decoration_v1};
+use super::pointer_constraints::{zwp_pointer_constraints_v1, zwp_locked_pointer_v1, zwp_confined_pointer_v1};
+use super::relative_pointer::{zwp_relative_pointer_manager_v1, zwp_relative_pointer_v1};
+use super::xdg_toplevel_icon::xdg_toplevel_icon_manager_v1;
+use super::primary_selection::{zwp_primary_selection_device_manager_v1, zwp_primary_selection_device_v1,
+                               zwp_primary_selection_offer_v1, zwp_primary_selection_source_v1};
+use super::software::ShmBuffer;
+
+use api::images::premultiplied_argb;
+use api::uri_list;
+
+// How long to wait for the source of a selection to send its data before giving up
+const SELECTION_TIMEOUT_MS: u64 = 1000;
+
+lazy_static! {
+    // the contexts of the hosts that embed our windows, along with their connection, which are
+    // shared by the windows of a host
+    static ref EMBEDDED_CONTEXTS: Mutex<Vec<(usize, Weak<WaylandContext>)>> = Mutex::new(Vec::new());
+}
 
 /*
  * Registry and globals handling
@@ -18,7 +54,6 @@ use super::keyboard::KbdHandler;
 
 wayland_env!(InnerEnv,
     compositor: wl_compositor::WlCompositor,
-    shell: wl_shell::WlShell,
     shm: wl_shm::WlShm,
     subcompositor: wl_subcompositor::WlSubcompositor
 );
@@ -41,8 +76,142 @@ struct WaylandEnv {
     axis_buffer: Option<(f32, f32)>,
     axis_discrete_buffer: Option<(i32, i32)>,
     axis_state: TouchPhase,
+    // serial of the last time the pointer entered one of our windows, needed to set its cursor
+    pointer_serial: u32,
+    // the windows whose cursor is hidden while the pointer is over them
+    hidden_cursor_windows: Vec<Arc<Mutex<VecDeque<Event>>>>,
+    // the cursors chosen by the windows, the others use the default one
+    window_cursors: Vec<(Arc<Mutex<VecDeque<Event>>>, WindowCursor)>,
+    // created the first time the pointer enters one of our windows
+    pointer_cursor: Option<PointerCursor>,
+    pointer_constraints: Option<zwp_pointer_constraints_v1::ZwpPointerConstraintsV1>,
+    relative_pointer_manager: Option<zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1>,
+    // reports the motion of the mouse even when the pointer is locked
+    relative_pointer: Option<zwp_relative_pointer_v1::ZwpRelativePointerV1>,
     kbd: Option<wl_keyboard::WlKeyboard>,
-    kbd_handler: KbdType
+    kbd_handler: KbdType,
+    touch: Option<wl_touch::WlTouch>,
+    // the fingers currently down on our windows
+    touch_points: Vec<TouchPoint>,
+    // the windows created with `multitouch`, the others don't get touch events
+    touch_windows: Vec<Arc<Mutex<VecDeque<Event>>>>,
+    // serial of the last input event, needed to set the selection
+    last_serial: u32,
+    data_device_manager: Option<wl_data_device_manager::WlDataDeviceManager>,
+    // compositors that support xdg_shell may not support wl_shell anymore
+    shell: Option<wl_shell::WlShell>,
+    // preferred over wl_shell when the compositor supports it
+    wm_base: Option<xdg_wm_base::XdgWmBase>,
+    // lets the compositor draw the decorations of xdg_shell windows
+    decoration_manager: Option<zxdg_decoration_manager_v1::ZxdgDecorationManagerV1>,
+    // sets the icons of xdg_shell windows
+    icon_manager: Option<xdg_toplevel_icon_manager_v1::XdgToplevelIconManagerV1>,
+    data_device: Option<wl_data_device::WlDataDevice>,
+    // offers introduced by the compositor, along with their mime types
+    data_offers: Vec<(wl_data_offer::WlDataOffer, Vec<String>)>,
+    selection_offer: Option<(wl_data_offer::WlDataOffer, Vec<String>)>,
+    selection_source: Option<(wl_data_source::WlDataSource, Vec<ClipboardData>)>,
+    // the primary selection is an extension, which works like the clipboard
+    primary_selection_manager: Option<zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1>,
+    primary_selection_device: Option<zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1>,
+    primary_offers: Vec<(zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1, Vec<String>)>,
+    primary_offer: Option<(zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1, Vec<String>)>,
+    primary_source: Option<(zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1, Vec<ClipboardData>)>,
+    // serial of the last button press, needed to start a drag
+    button_serial: u32,
+    drag_source: Option<DragSource>,
+    // drag coming from a client, currently over one of our windows
+    drag_offer: Option<DragOffer>,
+    // data of our selections and drags still being written to other clients
+    transfers: Vec<Transfer>,
+    // the connection belongs to a host that embeds our windows
+    embedded: bool,
+    parents: Vec<ParentSurface>
+}
+
+// The surface of the host an embedded window is a subsurface of. It was created by another
+// library, so it can't be wrapped in a `WlSurface`.
+struct ParentSurface {
+    surface: *mut wl_proxy,
+    eviter: Arc<Mutex<VecDeque<Event>>>
+}
+
+// the pointer is only compared, never dereferenced
+unsafe impl Send for ParentSurface {}
+
+// A finger on one of our windows. The compositor only names the surface when the finger
+// touches it.
+struct TouchPoint {
+    id: i32,
+    eviter: Arc<Mutex<VecDeque<Event>>>,
+    location: (f64, f64)
+}
+
+// A drag started by one of our windows
+struct DragSource {
+    source: wl_data_source::WlDataSource,
+    contents: Vec<ClipboardData>,
+    eviter: Arc<Mutex<VecDeque<Event>>>,
+    action: Option<DragAction>
+}
+
+// A drag over one of our windows
+struct DragOffer {
+    offer: wl_data_offer::WlDataOffer,
+    eviter: Arc<Mutex<VecDeque<Event>>>,
+    // whether the offer contains a list of files
+    has_uri_list: bool,
+    // the list of files is read as the source writes it, once the request has been sent
+    receiver: Option<File>,
+    received: Vec<u8>,
+    paths: Option<Vec<PathBuf>>,
+    location: (i32, i32),
+    dropped: bool
+}
+
+impl DragOffer {
+    fn send_hovered(&self) {
+        if let Some(ref paths) = self.paths {
+            let (x, y) = self.location;
+            let mut event_queue = self.eviter.lock().unwrap();
+            for path in paths {
+                event_queue.push_back(Event::HoveredFile(path.clone(), x, y));
+            }
+        }
+    }
+}
+
+// Data sent to another client through a pipe, as fast as it reads it
+struct Transfer {
+    file: File,
+    data: Vec<u8>,
+    written: usize
+}
+
+impl Transfer {
+    fn new(file: File, data: Vec<u8>) -> Transfer {
+        set_nonblocking(file.as_raw_fd());
+        Transfer {
+            file: file,
+            data: data,
+            written: 0
+        }
+    }
+
+    // Writes what the pipe can take. Returns `true` once everything was written, or once the
+    // receiver closed its end.
+    fn write_available(&mut self) -> bool {
+        while self.written < self.data.len() {
+            match write_without_sigpipe(self.file.as_raw_fd(), &self.data[self.written ..]) {
+                Ok(len) => self.written += len,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return false,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+                // including `EPIPE`, when the receiver doesn't want more
+                Err(_) => return true
+            }
+        }
+        true
+    }
 }
 
 struct OutputInfo {
@@ -66,7 +235,7 @@ impl OutputInfo {
 }
 
 impl WaylandEnv {
-    fn new(registry: wl_registry::WlRegistry) -> WaylandEnv {
+    fn new(registry: wl_registry::WlRegistry, embedded: bool) -> WaylandEnv {
         let kbd_handler = match MappedKeyboard::new(KbdHandler::new()) {
             Ok(h) => KbdType::Mapped(h),
             Err(_) => KbdType::Plain(None)
@@ -84,11 +253,244 @@ impl WaylandEnv {
             axis_buffer: None,
             axis_discrete_buffer: None,
             axis_state: TouchPhase::Started,
+            pointer_serial: 0,
+            hidden_cursor_windows: Vec::new(),
+            window_cursors: Vec::new(),
+            pointer_cursor: None,
+            pointer_constraints: None,
+            relative_pointer_manager: None,
+            relative_pointer: None,
             kbd: None,
-            kbd_handler: kbd_handler
+            kbd_handler: kbd_handler,
+            touch: None,
+            touch_points: Vec::new(),
+            touch_windows: Vec::new(),
+            last_serial: 0,
+            data_device_manager: None,
+            shell: None,
+            wm_base: None,
+            decoration_manager: None,
+            icon_manager: None,
+            data_device: None,
+            data_offers: Vec::new(),
+            selection_offer: None,
+            selection_source: None,
+            primary_selection_manager: None,
+            primary_selection_device: None,
+            primary_offers: Vec::new(),
+            primary_offer: None,
+            primary_source: None,
+            button_serial: 0,
+            drag_source: None,
+            drag_offer: None,
+            transfers: Vec::new(),
+            embedded: embedded,
+            parents: Vec::new()
+        }
+    }
+
+    // Writes `data` to another client through `file`. What the pipe can't take yet is written
+    // by `dispatch`, so that a slow client doesn't block our event loop.
+    fn send_data(&mut self, file: File, data: Vec<u8>) {
+        let mut transfer = Transfer::new(file, data);
+        if !transfer.write_available() {
+            self.transfers.push(transfer);
+        }
+    }
+
+    // Destroys the objects created on the connection, when it belongs to a host that keeps it
+    // open after our windows are gone.
+    fn destroy(&mut self) {
+        self.transfers.clear();
+        if let Some(drag) = self.drag_offer.take() {
+            drag.offer.destroy();
+        }
+        if let Some(drag) = self.drag_source.take() {
+            drag.source.destroy();
+        }
+        for (offer, _) in self.data_offers.drain(..) {
+            offer.destroy();
+        }
+        if let Some((offer, _)) = self.selection_offer.take() {
+            offer.destroy();
+        }
+        if let Some((source, _)) = self.selection_source.take() {
+            source.destroy();
+        }
+        if let Some(device) = self.data_device.take() {
+            // `release` was added in version 2
+            if device.version() >= 2 {
+                device.release();
+            } else {
+                unsafe { destroy_proxy(&device) };
+            }
+        }
+        if let Some(manager) = self.data_device_manager.take() {
+            unsafe { destroy_proxy(&manager) };
+        }
+
+        for (offer, _) in self.primary_offers.drain(..) {
+            offer.destroy();
+        }
+        if let Some((offer, _)) = self.primary_offer.take() {
+            offer.destroy();
+        }
+        if let Some((source, _)) = self.primary_source.take() {
+            source.destroy();
+        }
+        if let Some(device) = self.primary_selection_device.take() {
+            device.destroy();
+        }
+        if let Some(manager) = self.primary_selection_manager.take() {
+            manager.destroy();
+        }
+
+        if let Some(relative_pointer) = self.relative_pointer.take() {
+            relative_pointer.destroy();
+        }
+        if let Some(manager) = self.relative_pointer_manager.take() {
+            manager.destroy();
+        }
+        if let Some(constraints) = self.pointer_constraints.take() {
+            constraints.destroy();
+        }
+        if let Some(manager) = self.icon_manager.take() {
+            manager.destroy();
+        }
+        if let Some(manager) = self.decoration_manager.take() {
+            manager.destroy();
+        }
+        if let Some(wm_base) = self.wm_base.take() {
+            wm_base.destroy();
+        }
+        if let Some(shell) = self.shell.take() {
+            unsafe { destroy_proxy(&shell) };
+        }
+
+        // the seat is bound with version 5, which its devices share
+        self.pointer_cursor = None;
+        if let Some(pointer) = self.mouse.take() {
+            pointer.release();
+        }
+        if let Some(kbd) = self.kbd.take() {
+            kbd.release();
+        }
+        if let Some(touch) = self.touch.take() {
+            touch.release();
+        }
+        if let Some(seat) = self.seat.take() {
+            seat.release();
+        }
+
+        // the outputs are bound with version 1, before `release` was added
+        for monitor in self.monitors.drain(..) {
+            unsafe { destroy_proxy(&monitor.output) };
+        }
+        self.inner.subcompositor.destroy();
+        unsafe {
+            destroy_proxy(&self.inner.compositor);
+            destroy_proxy(&self.inner.shm);
+            destroy_proxy(&self.registry);
         }
     }
 
+    // Whether the surface was created by the host of our embedded windows
+    fn is_foreign_surface(&self, surface: *mut wl_proxy) -> bool {
+        self.embedded && !self.windows.iter().any(|&(ref window, _)| window.ptr() == surface)
+    }
+
+    // The data device can only be created once both the seat and the manager are known
+    fn init_data_device(&mut self, evqh: &mut EventQueueHandle) {
+        if self.data_device.is_some() {
+            return;
+        }
+        if let (Some(ref manager), Some(ref seat)) = (self.data_device_manager.as_ref(), self.seat.as_ref()) {
+            let device = manager.get_data_device(seat).expect("Data device manager cannot be dead");
+            evqh.register::<_, WaylandEnv>(&device, self.my_id);
+            self.data_device = Some(device);
+        }
+    }
+
+    fn init_primary_selection_device(&mut self, evqh: &mut EventQueueHandle) {
+        if self.primary_selection_device.is_some() {
+            return;
+        }
+        if let (Some(ref manager), Some(ref seat)) = (self.primary_selection_manager.as_ref(), self.seat.as_ref()) {
+            let device = manager.get_device(seat).expect("Primary selection device manager cannot be dead");
+            evqh.register::<_, WaylandEnv>(&device, self.my_id);
+            self.primary_selection_device = Some(device);
+        }
+    }
+
+    // Ends the drag started by one of our windows
+    fn finish_drag(&mut self, accepted: bool) {
+        if let Some(drag) = self.drag_source.take() {
+            // before version 3, every drag is a copy
+            let action = if drag.source.version() >= 3 { drag.action } else { Some(DragAction::Copy) };
+            drag.eviter.lock().unwrap().push_back(Event::DragFinished {
+                accepted: accepted,
+                action: if accepted { action } else { None }
+            });
+            drag.source.destroy();
+        }
+    }
+
+    // The contents of a selection owned by one of our windows
+    fn selection_contents(&self, selection: Selection) -> Option<&Vec<ClipboardData>> {
+        match selection {
+            Selection::Clipboard => self.selection_source.as_ref().map(|&(_, ref contents)| contents),
+            Selection::Primary => self.primary_source.as_ref().map(|&(_, ref contents)| contents)
+        }
+    }
+
+    // The mime types of a selection owned by another client
+    fn selection_mime_types(&self, selection: Selection) -> Option<&Vec<String>> {
+        match selection {
+            Selection::Clipboard => self.selection_offer.as_ref().map(|&(_, ref mime_types)| mime_types),
+            Selection::Primary => self.primary_offer.as_ref().map(|&(_, ref mime_types)| mime_types)
+        }
+    }
+
+    // The relative pointer can only be created once both the pointer and the manager are known
+    fn init_relative_pointer(&mut self, evqh: &mut EventQueueHandle) {
+        if self.relative_pointer.is_some() {
+            return;
+        }
+        if let (Some(ref manager), Some(ref pointer)) = (self.relative_pointer_manager.as_ref(), self.mouse.as_ref()) {
+            let relative_pointer = manager.get_relative_pointer(pointer)
+                                          .expect("Relative pointer manager cannot be dead");
+            evqh.register::<_, WaylandEnv>(&relative_pointer, self.my_id);
+            self.relative_pointer = Some(relative_pointer);
+        }
+    }
+
+    // Shows the cursor of the window under the pointer
+    fn update_cursor(&mut self) {
+        let focus = match self.mouse_focus {
+            Some(ref focus) => focus.clone(),
+            None => return
+        };
+        let pointer = match self.mouse {
+            Some(ref pointer) => pointer,
+            None => return
+        };
+        let cursor = if self.hidden_cursor_windows.iter().any(|w| Arc::ptr_eq(w, &focus)) {
+            WindowCursor::Themed(MouseCursor::NoneCursor)
+        } else {
+            self.window_cursors.iter().find(|&&(ref w, _)| Arc::ptr_eq(w, &focus))
+                                      .map(|&(_, ref cursor)| cursor.clone())
+                                      .unwrap_or(WindowCursor::Themed(MouseCursor::Default))
+        };
+        // the images are drawn for the largest scale, shrunk on the other outputs
+        let scale = self.monitors.iter().map(|m| m.scale as i32).max().unwrap_or(1);
+        if self.pointer_cursor.is_none() {
+            let surface = self.inner.compositor.create_surface().expect("Compositor cannot be dead");
+            self.pointer_cursor = Some(PointerCursor::new(surface));
+        }
+        self.pointer_cursor.as_mut().unwrap().set_cursor(pointer, self.pointer_serial, &cursor,
+                                                         ::std::cmp::max(scale, 1), &self.inner.shm);
+    }
+
     fn get_seat(&self) -> Option<wl_seat::WlSeat> {
         for &(name, ref interface, version) in self.inner.globals() {
             if interface == "wl_seat" {
@@ -131,6 +533,48 @@ impl wl_registry::Handler for WaylandEnv {
                            .expect("Registry cannot be dead");
             evqh.register::<_, WaylandEnv>(&seat, self.my_id);
             self.seat = Some(seat);
+            self.init_data_device(evqh);
+            self.init_primary_selection_device(evqh);
+        } else if interface == "wl_data_device_manager" {
+            let manager = self.registry.bind::<wl_data_device_manager::WlDataDeviceManager>(
+                ::std::cmp::min(version, 3), name
+            ).expect("Registry cannot be dead");
+            self.data_device_manager = Some(manager);
+            self.init_data_device(evqh);
+        } else if interface == "zwp_primary_selection_device_manager_v1" {
+            let manager = self.registry.bind::<zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1>(1, name)
+                              .expect("Registry cannot be dead");
+            self.primary_selection_manager = Some(manager);
+            self.init_primary_selection_device(evqh);
+        } else if interface == "wl_shell" && !self.embedded {
+            // embedded windows have no shell surface
+            let shell = self.registry.bind::<wl_shell::WlShell>(1, name)
+                            .expect("Registry cannot be dead");
+            self.shell = Some(shell);
+        } else if interface == "xdg_wm_base" && !self.embedded {
+            // embedded windows have no shell surface
+            let wm_base = self.registry.bind::<xdg_wm_base::XdgWmBase>(::std::cmp::min(version, 2), name)
+                              .expect("Registry cannot be dead");
+            evqh.register::<_, WaylandEnv>(&wm_base, self.my_id);
+            self.wm_base = Some(wm_base);
+        } else if interface == "zxdg_decoration_manager_v1" && !self.embedded {
+            let manager = self.registry.bind::<zxdg_decoration_manager_v1::ZxdgDecorationManagerV1>(1, name)
+                              .expect("Registry cannot be dead");
+            self.decoration_manager = Some(manager);
+        } else if interface == "xdg_toplevel_icon_manager_v1" && !self.embedded {
+            let manager = self.registry.bind::<xdg_toplevel_icon_manager_v1::XdgToplevelIconManagerV1>(1, name)
+                              .expect("Registry cannot be dead");
+            self.icon_manager = Some(manager);
+        } else if interface == "zwp_pointer_constraints_v1" && !self.embedded {
+            // the pointer of embedded windows belongs to their host
+            let constraints = self.registry.bind::<zwp_pointer_constraints_v1::ZwpPointerConstraintsV1>(1, name)
+                                  .expect("Registry cannot be dead");
+            self.pointer_constraints = Some(constraints);
+        } else if interface == "zwp_relative_pointer_manager_v1" && !self.embedded {
+            let manager = self.registry.bind::<zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1>(1, name)
+                              .expect("Registry cannot be dead");
+            self.relative_pointer_manager = Some(manager);
+            self.init_relative_pointer(evqh);
         }
         self.inner.global(evqh, registry, name, interface, version);
     }
@@ -148,6 +592,19 @@ impl wl_registry::Handler for WaylandEnv {
 
 declare_handler!(WaylandEnv, wl_registry::Handler, wl_registry::WlRegistry);
 
+impl xdg_wm_base::Handler for WaylandEnv {
+    fn ping(&mut self,
+            _: &mut EventQueueHandle,
+            proxy: &xdg_wm_base::XdgWmBase,
+            serial: u32)
+    {
+        // the compositor considers clients that don't answer as unresponsive
+        proxy.pong(serial);
+    }
+}
+
+declare_handler!(WaylandEnv, xdg_wm_base::Handler, xdg_wm_base::XdgWmBase);
+
 impl wl_output::Handler for WaylandEnv {
     fn geometry(&mut self,
                 _: &mut EventQueueHandle,
@@ -196,46 +653,207 @@ declare_handler!(WaylandEnv, wl_output::Handler, wl_output::WlOutput);
  */
 
 pub struct WaylandContext {
-    pub display: wl_display::WlDisplay,
+    // the connection of a host is left open
+    pub display: ManuallyDrop<wl_display::WlDisplay>,
     evq: Mutex<EventQueue>,
     env_id: usize,
+    embedded: bool,
 }
 
 impl WaylandContext {
     pub fn init() -> Option<WaylandContext> {
         // attempt to connect to the wayland server
         // this handles both "no libwayland" and "no compositor" cases
-        let (display, mut event_queue) = match default_connect() {
+        let (display, event_queue) = match default_connect() {
             Ok(ret) => ret,
             Err(e) => return None
         };
 
+        Some(WaylandContext::new(display, event_queue, false))
+    }
+
+    /// Returns the context that uses the connection of a host, to create windows embedded in its
+    /// surfaces. The windows of a host share a context, which lives as long as one of them.
+    pub fn init_embedded(display: *mut c_void) -> Option<Arc<WaylandContext>> {
+        if display.is_null() {
+            return None;
+        }
+
+        let mut contexts = EMBEDDED_CONTEXTS.lock().unwrap();
+        contexts.retain(|&(_, ref ctxt)| ctxt.upgrade().is_some());
+        let existing = contexts.iter().find(|&&(d, _)| d == display as usize)
+                               .and_then(|&(_, ref ctxt)| ctxt.upgrade());
+        if let Some(ctxt) = existing {
+            return Some(ctxt);
+        }
+
+        let wl_display = unsafe { wl_display::WlDisplay::from_ptr_new(display as *mut wl_proxy) };
+        // the default queue is dispatched by the host
+        let event_queue = wl_display.create_event_queue();
+
+        let ctxt = Arc::new(WaylandContext::new(wl_display, event_queue, true));
+        contexts.push((display as usize, Arc::downgrade(&ctxt)));
+        Some(ctxt)
+    }
+
+    fn new(display: wl_display::WlDisplay, mut event_queue: EventQueue, embedded: bool) -> WaylandContext {
         // this "expect" cannot trigger (see https://github.com/vberger/wayland-client-rs/issues/69)
         let registry = display.get_registry().expect("Display cannot be already destroyed.");
-        let env_id = event_queue.add_handler_with_init(WaylandEnv::new(registry));
+        let env_id = event_queue.add_handler_with_init(WaylandEnv::new(registry, embedded));
         // two syncs fully initialize
         event_queue.sync_roundtrip().expect("Wayland connection unexpectedly lost");
         event_queue.sync_roundtrip().expect("Wayland connection unexpectedly lost");
 
-        Some(WaylandContext {
+        WaylandContext {
             evq: Mutex::new(event_queue),
-            display: display,
-            env_id: env_id
-        })
+            display: ManuallyDrop::new(display),
+            env_id: env_id,
+            embedded: embedded
+        }
     }
 
     pub fn dispatch_pending(&self) {
         let mut guard = self.evq.lock().unwrap();
         guard.dispatch_pending().expect("Wayland connection unexpectedly lost");
+        self.process_drag_offer(&mut guard);
+        self.process_transfers(&mut guard);
+        self.process_key_repeat(&mut guard);
     }
 
+    /// Waits for events and dispatches them. The wait ends early when a held key is due to be
+    /// repeated, when the source of the drag over our windows sent more of its data, or when a
+    /// client reading our data can take more of it.
     pub fn dispatch(&self) {
         let mut guard = self.evq.lock().unwrap();
-        guard.dispatch().expect("Wayland connection unexpectedly lost");
+        let timeout = self.key_repeat_timeout(&mut guard);
+        let drag_fd = self.drag_offer_fd(&mut guard);
+        let transfer_fds = self.transfer_fds(&mut guard);
+        if timeout.is_none() && drag_fd.is_none() && transfer_fds.is_empty() {
+            guard.dispatch().expect("Wayland connection unexpectedly lost");
+        } else {
+            if let Some(read_guard) = guard.prepare_read() {
+                let _ = self.display.flush();
+                // `poll` ignores the negative file descriptors
+                let mut pollfds = vec![
+                    libc::pollfd { fd: self.display.get_fd(), events: libc::POLLIN, revents: 0 },
+                    libc::pollfd { fd: drag_fd.unwrap_or(-1), events: libc::POLLIN, revents: 0 }
+                ];
+                pollfds.extend(transfer_fds.iter().map(|&fd| libc::pollfd { fd: fd, events: libc::POLLOUT, revents: 0 }));
+                let timeout = timeout.map(poll_timeout).unwrap_or(-1);
+                unsafe { libc::poll(pollfds.as_mut_ptr(), pollfds.len() as libc::nfds_t, timeout) };
+                if pollfds[0].revents != 0 {
+                    read_guard.read_events().expect("Wayland connection unexpectedly lost");
+                } else {
+                    read_guard.cancel();
+                }
+            }
+            guard.dispatch_pending().expect("Wayland connection unexpectedly lost");
+        }
+        self.process_drag_offer(&mut guard);
+        self.process_transfers(&mut guard);
+        self.process_key_repeat(&mut guard);
+    }
+
+    // The pipes through which other clients read our data
+    fn transfer_fds(&self, evq: &mut EventQueue) -> Vec<RawFd> {
+        let state = evq.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        env.transfers.iter().map(|transfer| transfer.file.as_raw_fd()).collect()
+    }
+
+    // Writes the data that the pipes of other clients can now take. The pipes are closed once
+    // everything was written, which tells the clients that the data is complete.
+    fn process_transfers(&self, evq: &mut EventQueue) {
+        let mut state = evq.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        let mut i = 0;
+        while i < env.transfers.len() {
+            if env.transfers[i].write_available() {
+                env.transfers.remove(i);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
+    // The pipe through which the source of the drag over our windows sends its files
+    fn drag_offer_fd(&self, evq: &mut EventQueue) -> Option<RawFd> {
+        let state = evq.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        env.drag_offer.as_ref().and_then(|drag| drag.receiver.as_ref()).map(|receiver| receiver.as_raw_fd())
+    }
+
+    fn key_repeat_timeout(&self, evq: &mut EventQueue) -> Option<::std::time::Duration> {
+        let mut state = evq.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        match env.kbd_handler {
+            KbdType::Mapped(ref mut h) => h.handler().repeat_timeout(),
+            KbdType::Plain(_) => None
+        }
+    }
+
+    // Sends the repeat of the held key, which the compositor leaves to us.
+    fn process_key_repeat(&self, evq: &mut EventQueue) {
+        let mut state = evq.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        if let KbdType::Mapped(ref mut h) = env.kbd_handler {
+            h.handler().poll_repeat();
+        }
+    }
+
+    // Reads what the source of the drag over our windows sent of its files, which can't be done
+    // from the event handlers since the request has to be flushed first, and delivers the drop
+    // once they are all known.
+    fn process_drag_offer(&self, evq: &mut EventQueue) {
+        let mut state = evq.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+
+        let finished = match env.drag_offer {
+            Some(ref mut drag) => {
+                let complete = match drag.receiver {
+                    Some(ref mut receiver) => {
+                        let _ = self.display.flush();
+                        // the source closes its end once everything has been written
+                        read_available(receiver, &mut drag.received).unwrap_or(true)
+                    },
+                    None => false
+                };
+                if complete {
+                    drag.receiver = None;
+                    drag.paths = Some(uri_list::parse_uri_list(&mem::replace(&mut drag.received, Vec::new())));
+                    if !drag.dropped {
+                        drag.send_hovered();
+                    }
+                }
+
+                if drag.dropped && drag.receiver.is_none() {
+                    if let Some(ref paths) = drag.paths {
+                        let mut event_queue = drag.eviter.lock().unwrap();
+                        for path in paths {
+                            event_queue.push_back(Event::DroppedFile(path.clone()));
+                        }
+                    }
+                    // tells the source that the operation succeeded
+                    if drag.offer.version() >= 3 {
+                        drag.offer.finish();
+                    }
+                    drag.offer.destroy();
+                    let _ = self.display.flush();
+                    true
+                } else {
+                    false
+                }
+            },
+            None => false
+        };
+
+        if finished {
+            env.drag_offer = None;
+        }
     }
 
     pub fn flush(&self) {
-        self.display.flush();
+        let _ = self.display.flush();
     }
 
     pub fn with_output<F>(&self, id: MonitorId, f: F) where F: FnOnce(&wl_output::WlOutput) {
@@ -248,12 +866,27 @@ impl WaylandContext {
         }
     }
 
+    pub fn create_shm_pool(&self, fd: ::std::os::unix::io::RawFd, size: i32) -> wl_shm_pool::WlShmPool {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        env.inner.shm.create_pool(fd, size).expect("Shm cannot be dead")
+    }
+
+    /// Creates the surface of a toplevel window through `wl_shell`, along with the decorations
+    /// drawn around it.
+    ///
+    /// Returns `None` if the compositor doesn't support `wl_shell`.
     pub fn create_window<H: wayland_window::Handler>(&self)
-        -> (Arc<wl_surface::WlSurface>, Arc<Mutex<VecDeque<Event>>>, wayland_window::DecoratedSurface<H>)
+        -> Option<(Arc<wl_surface::WlSurface>, Arc<Mutex<VecDeque<Event>>>, wayland_window::DecoratedSurface<H>)>
     {
         let mut guard = self.evq.lock().unwrap();
         let mut state = guard.state();
         let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        let shell = match env.shell {
+            Some(ref shell) => shell,
+            None => return None
+        };
         // this "expect" cannot trigger (see https://github.com/vberger/wayland-client-rs/issues/69)
         let surface = Arc::new(env.inner.compositor.create_surface().expect("Compositor cannot be dead"));
         let eventiter = Arc::new(Mutex::new(VecDeque::new()));
@@ -263,18 +896,471 @@ impl WaylandContext {
             &env.inner.compositor,
             &env.inner.subcompositor,
             &env.inner.shm,
-            &env.inner.shell,
+            shell,
             env.get_seat(),
             false
         ).expect("Failed to create a tmpfile buffer.");
-        (surface, eventiter, decorated)
+        Some((surface, eventiter, decorated))
+    }
+
+    /// Creates the surface of a toplevel window through `xdg_shell`, along with the object that
+    /// negotiates its decorations if the compositor can draw them.
+    ///
+    /// Returns `None` if the compositor doesn't support `xdg_shell`.
+    pub fn create_xdg_window(&self)
+        -> Option<(Arc<wl_surface::WlSurface>, Arc<Mutex<VecDeque<Event>>>, xdg_surface::XdgSurface,
+                   xdg_toplevel::XdgToplevel, Option<zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1>)>
+    {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        let wm_base = match env.wm_base {
+            Some(ref wm_base) => wm_base,
+            None => return None
+        };
+        let surface = Arc::new(env.inner.compositor.create_surface().expect("Compositor cannot be dead"));
+        let eventiter = Arc::new(Mutex::new(VecDeque::new()));
+        env.windows.push((surface.clone(), eventiter.clone()));
+        let xdg_surface = wm_base.get_xdg_surface(&surface).expect("Shell cannot be dead");
+        let toplevel = xdg_surface.get_toplevel().expect("Shell surface cannot be dead");
+        // must be created before the first commit
+        let decoration = env.decoration_manager.as_ref().map(|manager| {
+            manager.get_toplevel_decoration(&toplevel).expect("Decoration manager cannot be dead")
+        });
+        Some((surface, eventiter, xdg_surface, toplevel, decoration))
+    }
+
+    /// Creates the borders drawn around an xdg_shell window when the compositor doesn't draw its
+    /// decorations. They are hidden until the window shows them.
+    pub fn create_frame(&self, surface: &wl_surface::WlSurface, size: (u32, u32)) -> Frame {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        let surfaces: Vec<_> = (0..4).map(|_| {
+            env.inner.compositor.create_surface().expect("Compositor cannot be dead")
+        }).collect();
+        let subsurfaces = surfaces.iter().map(|s| {
+            env.inner.subcompositor.get_subsurface(s, surface).expect("Subcompositor cannot be dead")
+        }).collect();
+        let cursor = PointerCursor::new(env.inner.compositor.create_surface().expect("Compositor cannot be dead"));
+        // the images are drawn for the largest scale, like the cursor over the windows
+        let scale = env.monitors.iter().map(|m| m.scale as i32).max().unwrap_or(1);
+        // the frame creates its buffers from within the event handlers of the window
+        let shm = unsafe { wl_shm::WlShm::from_ptr_initialized(env.inner.shm.ptr()) };
+        Frame::new(surfaces, subsurfaces, shm, env.get_seat(), cursor, ::std::cmp::max(scale, 1), size)
+    }
+
+    /// Creates the surface of a window embedded in `parent`, a surface of the host.
+    pub fn create_subsurface(&self, parent: *mut c_void)
+        -> (Arc<wl_surface::WlSurface>, Arc<Mutex<VecDeque<Event>>>, wl_subsurface::WlSubsurface)
+    {
+        // opcode of `wl_subcompositor.get_subsurface`
+        const GET_SUBSURFACE: u32 = 1;
+
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        let surface = Arc::new(env.inner.compositor.create_surface().expect("Compositor cannot be dead"));
+        let eventiter = Arc::new(Mutex::new(VecDeque::new()));
+        env.windows.push((surface.clone(), eventiter.clone()));
+        env.parents.push(ParentSurface { surface: parent as *mut wl_proxy, eviter: eventiter.clone() });
+
+        // the parent can't be wrapped in a `WlSurface`, so the request is sent directly
+        let subsurface = unsafe {
+            let subsurface = (WAYLAND_CLIENT_HANDLE.wl_proxy_marshal_constructor)(
+                env.inner.subcompositor.ptr(), GET_SUBSURFACE,
+                wl_subsurface::WlSubsurface::interface_ptr(), ptr::null_mut::<wl_proxy>(),
+                surface.ptr(), parent as *mut wl_proxy
+            );
+            wl_subsurface::WlSubsurface::from_ptr_new(subsurface)
+        };
+        // our commits are shown without waiting for the host to commit its surface
+        subsurface.set_desync();
+        (surface, eventiter, subsurface)
+    }
+
+    pub fn set_selection(&self, selection: Selection, contents: Vec<ClipboardData>) {
+        match selection {
+            Selection::Clipboard => self.set_clipboard(contents),
+            Selection::Primary => self.set_primary_selection(contents)
+        }
+    }
+
+    fn set_clipboard(&self, contents: Vec<ClipboardData>) {
+        let mut guard = self.evq.lock().unwrap();
+        let source = {
+            let mut state = guard.state();
+            let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+            match env.data_device_manager {
+                Some(ref manager) => manager.create_data_source().expect("Data device manager cannot be dead"),
+                None => return
+            }
+        };
+        guard.register::<_, WaylandEnv>(&source, self.env_id);
+
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        for content in &contents {
+            source.offer(content.mime_type.clone());
+        }
+        if let Some(ref device) = env.data_device {
+            device.set_selection(Some(&source), env.last_serial);
+        }
+        if let Some((old_source, _)) = mem::replace(&mut env.selection_source, Some((source, contents))) {
+            old_source.destroy();
+        }
+    }
+
+    fn set_primary_selection(&self, contents: Vec<ClipboardData>) {
+        let mut guard = self.evq.lock().unwrap();
+        let source = {
+            let mut state = guard.state();
+            let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+            match env.primary_selection_manager {
+                Some(ref manager) => manager.create_source().expect("Primary selection device manager cannot be dead"),
+                None => {
+                    warn!("[winit] The compositor doesn't support the primary selection.");
+                    return
+                }
+            }
+        };
+        guard.register::<_, WaylandEnv>(&source, self.env_id);
+
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        for content in &contents {
+            source.offer(content.mime_type.clone());
+        }
+        if let Some(ref device) = env.primary_selection_device {
+            device.set_selection(Some(&source), env.last_serial);
+        }
+        if let Some((old_source, _)) = mem::replace(&mut env.primary_source, Some((source, contents))) {
+            old_source.destroy();
+        }
+    }
+
+    pub fn get_selection(&self, selection: Selection, mime_type: &str) -> Option<Vec<u8>> {
+        let mut file = {
+            let mut guard = self.evq.lock().unwrap();
+            let state = guard.state();
+            let env = state.get_handler::<WaylandEnv>(self.env_id);
+
+            // the compositor would ask us to write to the pipe we are reading from
+            if let Some(contents) = env.selection_contents(selection) {
+                return contents.iter().find(|c| c.mime_type == mime_type).map(|c| c.data.clone());
+            }
+
+            if !env.selection_mime_types(selection).map_or(false, |m| m.iter().any(|m| m == mime_type)) {
+                return None;
+            }
+
+            let mut fds = [0; 2];
+            if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+                return None;
+            }
+            match selection {
+                Selection::Clipboard => { env.selection_offer.as_ref().unwrap().0.receive(mime_type.into(), fds[1]); },
+                Selection::Primary => { env.primary_offer.as_ref().unwrap().0.receive(mime_type.into(), fds[1]); }
+            }
+            unsafe { libc::close(fds[1]) };
+            let _ = self.display.flush();
+            unsafe { File::from_raw_fd(fds[0]) }
+        };
+
+        // the events keep being dispatched while the source writes, which it may never finish
+        read_pipe(&mut file, Duration::from_millis(SELECTION_TIMEOUT_MS))
+    }
+
+    pub fn get_selection_mime_types(&self, selection: Selection) -> Vec<String> {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        if let Some(contents) = env.selection_contents(selection) {
+            contents.iter().map(|c| c.mime_type.clone()).collect()
+        } else {
+            env.selection_mime_types(selection).cloned().unwrap_or_else(Vec::new)
+        }
+    }
+
+    pub fn start_drag(&self, surface: &wl_surface::WlSurface, eviter: Arc<Mutex<VecDeque<Event>>>,
+                      contents: Vec<ClipboardData>)
+    {
+        let mut guard = self.evq.lock().unwrap();
+        let source = {
+            let mut state = guard.state();
+            let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+            // before version 3, we aren't told when a drop fails, the drag is over once the
+            // button that started it was released
+            if env.drag_source.as_ref().map_or(false, |drag| drag.source.version() < 3) {
+                env.finish_drag(false);
+            }
+            if env.drag_source.is_some() {
+                return;
+            }
+            match env.data_device_manager {
+                Some(ref manager) => manager.create_data_source().expect("Data device manager cannot be dead"),
+                None => return
+            }
+        };
+        guard.register::<_, WaylandEnv>(&source, self.env_id);
+
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        for content in &contents {
+            source.offer(content.mime_type.clone());
+        }
+        // actions were introduced in version 3, before that every drag is a copy
+        if source.version() >= 3 {
+            let actions = wl_data_device_manager::Copy | wl_data_device_manager::Move;
+            source.set_actions(actions.to_raw());
+        }
+        match env.data_device {
+            // the serial must be the one of the button press that started the drag
+            Some(ref device) => { device.start_drag(Some(&source), surface, None, env.button_serial); },
+            None => {
+                source.destroy();
+                return;
+            }
+        }
+        env.drag_source = Some(DragSource {
+            source: source,
+            contents: contents,
+            eviter: eviter,
+            action: None
+        });
+    }
+
+    pub fn prune_dead_windows(&self) {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        env.windows.retain(|w| w.0.is_alive());
+        let windows = &env.windows;
+        env.parents.retain(|p| windows.iter().any(|w| Arc::ptr_eq(&w.1, &p.eviter)));
+        env.touch_windows.retain(|eviter| windows.iter().any(|w| Arc::ptr_eq(&w.1, eviter)));
+        env.hidden_cursor_windows.retain(|eviter| windows.iter().any(|w| Arc::ptr_eq(&w.1, eviter)));
+        env.window_cursors.retain(|&(ref eviter, _)| windows.iter().any(|w| Arc::ptr_eq(&w.1, eviter)));
+    }
+
+    /// Whether the seat has a keyboard, which reports the focus of the windows.
+    pub fn has_keyboard(&self) -> bool {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        state.get_mut_handler::<WaylandEnv>(self.env_id).kbd.is_some()
+    }
+
+    /// Sends the touch events on the window of `eviter` to it.
+    pub fn enable_touch(&self, eviter: &Arc<Mutex<VecDeque<Event>>>) {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        env.touch_windows.push(eviter.clone());
+    }
+
+    /// Hides or shows the cursor while the pointer is over the window of `eviter`.
+    pub fn set_cursor_hidden(&self, eviter: &Arc<Mutex<VecDeque<Event>>>, hidden: bool) {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        env.hidden_cursor_windows.retain(|w| !Arc::ptr_eq(w, eviter));
+        if hidden {
+            env.hidden_cursor_windows.push(eviter.clone());
+        }
+        // otherwise the cursor changes on the next enter
+        if env.mouse_focus.as_ref().map(|f| Arc::ptr_eq(f, eviter)).unwrap_or(false) {
+            env.update_cursor();
+        }
+    }
+
+    /// Sets the cursor shown while the pointer is over the window of `eviter`.
+    pub fn set_cursor(&self, eviter: &Arc<Mutex<VecDeque<Event>>>, cursor: MouseCursor) {
+        self.set_window_cursor(eviter, WindowCursor::Themed(cursor));
+    }
+
+    /// Sets the image shown as the cursor while the pointer is over the window of `eviter`.
+    pub fn set_custom_cursor(&self, eviter: &Arc<Mutex<VecDeque<Event>>>, cursor: &CustomCursor) {
+        self.set_window_cursor(eviter, WindowCursor::Custom(cursor.clone()));
+    }
+
+    fn set_window_cursor(&self, eviter: &Arc<Mutex<VecDeque<Event>>>, cursor: WindowCursor) {
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
+        env.window_cursors.retain(|&(ref w, _)| !Arc::ptr_eq(w, eviter));
+        env.window_cursors.push((eviter.clone(), cursor));
+        if env.mouse_focus.as_ref().map(|f| Arc::ptr_eq(f, eviter)).unwrap_or(false) {
+            env.update_cursor();
+        }
+    }
+
+    /// Sets the icon of an xdg_shell window, which is applied on the next commit of its surface.
+    /// Returns `false` if the compositor doesn't support it.
+    pub fn set_toplevel_icon(&self, toplevel: &xdg_toplevel::XdgToplevel, icon: &[Icon]) -> bool {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        let manager = match env.icon_manager {
+            Some(ref manager) => manager,
+            None => return false
+        };
+        if icon.is_empty() {
+            manager.set_icon(toplevel, None);
+            return true;
+        }
+
+        // the compositor only takes square images
+        let buffers: Vec<ShmBuffer> = icon.iter()
+            .filter(|image| image.width == image.height && image.width != 0)
+            .map(|image| {
+                let mut buffer = ShmBuffer::with_shm(&env.inner.shm, image.width, image.height, true);
+                buffer.write_argb(&premultiplied_argb(&image.rgba, image.width, image.height));
+                buffer
+            })
+            .collect();
+        if buffers.len() < icon.len() {
+            warn!("[winit] Some images of the window icon aren't square and were ignored.");
+        }
+        let xdg_icon = manager.create_icon().expect("Icon manager cannot be dead");
+        for buffer in &buffers {
+            xdg_icon.add_buffer(buffer.get_buffer(), 1);
+        }
+        manager.set_icon(toplevel, Some(&xdg_icon));
+        // the compositor keeps its copy of the icon, and the buffers can go along with it
+        xdg_icon.destroy();
+        true
+    }
+
+    /// Keeps the pointer in place while it is over `surface`, returns `None` if the compositor
+    /// doesn't support it.
+    pub fn lock_pointer(&self, surface: &wl_surface::WlSurface) -> Option<zwp_locked_pointer_v1::ZwpLockedPointerV1> {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        match (env.pointer_constraints.as_ref(), env.mouse.as_ref()) {
+            (Some(constraints), Some(pointer)) => Some(constraints.lock_pointer(
+                surface, pointer, None, zwp_pointer_constraints_v1::Lifetime::Persistent.to_raw()
+            ).expect("Pointer constraints cannot be dead")),
+            _ => None
+        }
+    }
+
+    /// Keeps the pointer inside `surface` once it is over it, returns `None` if the compositor
+    /// doesn't support it.
+    pub fn confine_pointer(&self, surface: &wl_surface::WlSurface) -> Option<zwp_confined_pointer_v1::ZwpConfinedPointerV1> {
+        let mut guard = self.evq.lock().unwrap();
+        let state = guard.state();
+        let env = state.get_handler::<WaylandEnv>(self.env_id);
+        match (env.pointer_constraints.as_ref(), env.mouse.as_ref()) {
+            (Some(constraints), Some(pointer)) => Some(constraints.confine_pointer(
+                surface, pointer, None, zwp_pointer_constraints_v1::Lifetime::Persistent.to_raw()
+            ).expect("Pointer constraints cannot be dead")),
+            _ => None
+        }
+    }
+}
+
+// Converts a timeout to milliseconds for `poll`, rounding up so that it doesn't end early.
+fn poll_timeout(timeout: Duration) -> libc::c_int {
+    timeout.as_secs() as libc::c_int * 1000 + (timeout.subsec_nanos() as libc::c_int + 999_999) / 1_000_000
+}
+
+fn set_nonblocking(fd: RawFd) {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK);
+    }
+}
+
+// Appends what can be read from a non-blocking pipe to `data`. Returns `true` once the writer
+// has closed its end, which it does after writing everything.
+fn read_available(file: &mut File, data: &mut Vec<u8>) -> io::Result<bool> {
+    let mut buffer = [0u8; 4096];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(true),
+            Ok(len) => data.extend_from_slice(&buffer[.. len]),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(e)
+        }
+    }
+}
+
+// Writes to a pipe without raising `SIGPIPE` when the receiver closed its end, which would kill
+// the host of our windows if it doesn't ignore the signal. The signal is blocked for the thread
+// while writing, and discarded if the write raised it.
+fn write_without_sigpipe(fd: RawFd, data: &[u8]) -> io::Result<usize> {
+    unsafe {
+        let mut sigpipe: libc::sigset_t = mem::zeroed();
+        libc::sigemptyset(&mut sigpipe);
+        libc::sigaddset(&mut sigpipe, libc::SIGPIPE);
+        // a signal that was already pending isn't ours to discard
+        let mut pending: libc::sigset_t = mem::zeroed();
+        libc::sigpending(&mut pending);
+        let was_pending = libc::sigismember(&pending, libc::SIGPIPE) == 1;
+        let mut old_mask: libc::sigset_t = mem::zeroed();
+        libc::pthread_sigmask(libc::SIG_BLOCK, &sigpipe, &mut old_mask);
+
+        let len = libc::write(fd, data.as_ptr() as *const c_void, data.len());
+        let result = if len < 0 { Err(io::Error::last_os_error()) } else { Ok(len as usize) };
+
+        if !was_pending && result.as_ref().err().and_then(|e| e.raw_os_error()) == Some(libc::EPIPE) {
+            // the signal is pending for the thread, so this returns right away
+            let mut signal = 0;
+            libc::sigwait(&sigpipe, &mut signal);
+        }
+        libc::pthread_sigmask(libc::SIG_SETMASK, &old_mask, ptr::null_mut());
+        result
     }
+}
 
-    pub fn prune_dead_windows(&self) {
+// Reads the data sent by the source of an offer through `file`, or gives up after `timeout`.
+fn read_pipe(file: &mut File, timeout: Duration) -> Option<Vec<u8>> {
+    set_nonblocking(file.as_raw_fd());
+    let deadline = Instant::now() + timeout;
+    let mut data = Vec::new();
+    loop {
+        match read_available(file, &mut data) {
+            Ok(true) => return Some(data),
+            Ok(false) => (),
+            Err(_) => return None
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            warn!("[winit] The source of the selection didn't send its data in time.");
+            return None;
+        }
+        let mut pollfd = libc::pollfd { fd: file.as_raw_fd(), events: libc::POLLIN, revents: 0 };
+        unsafe { libc::poll(&mut pollfd, 1, poll_timeout(deadline - now)) };
+    }
+}
+
+// Destroys a proxy whose interface has no destructor request, the way the generated destructors
+// do.
+unsafe fn destroy_proxy<P: Proxy>(proxy: &P) {
+    let data: Box<(*mut c_void, Arc<(AtomicBool, AtomicPtr<()>)>)> =
+        Box::from_raw((WAYLAND_CLIENT_HANDLE.wl_proxy_get_user_data)(proxy.ptr()) as *mut _);
+    (data.1).0.store(false, Ordering::SeqCst);
+    (WAYLAND_CLIENT_HANDLE.wl_proxy_destroy)(proxy.ptr());
+}
+
+impl Drop for WaylandContext {
+    fn drop(&mut self) {
+        if !self.embedded {
+            unsafe { ManuallyDrop::drop(&mut self.display) };
+            return;
+        }
+
+        // the connection of the host stays open, so our objects have to be destroyed
         let mut guard = self.evq.lock().unwrap();
-        let mut state = guard.state();
-        let env = state.get_mut_handler::<WaylandEnv>(self.env_id);
-        env.windows.retain(|w| w.0.is_alive());
+        {
+            let mut state = guard.state();
+            state.get_mut_handler::<WaylandEnv>(self.env_id).destroy();
+        }
+        let _ = self.display.flush();
+        // wayland-client doesn't give access to the queue to destroy it, but nothing is left
+        // on it once the events for the destroyed objects are discarded
+        let _ = guard.dispatch_pending();
     }
 }
 
@@ -355,9 +1441,13 @@ impl wl_seat::Handler for WaylandEnv {
             let pointer = seat.get_pointer().expect("Seat is not dead");
             evqh.register::<_, WaylandEnv>(&pointer, self.my_id);
             self.mouse = Some(pointer);
+            self.init_relative_pointer(evqh);
         }
         // destroy pointer if applicable
         if !capabilities.contains(wl_seat::Pointer) {
+            if let Some(relative_pointer) = self.relative_pointer.take() {
+                relative_pointer.destroy();
+            }
             if let Some(pointer) = self.mouse.take() {
                 pointer.release();
             }
@@ -374,6 +1464,18 @@ impl wl_seat::Handler for WaylandEnv {
                 kbd.release();
             }
         }
+        // create touch if applicable
+        if capabilities.contains(wl_seat::Touch) && self.touch.is_none() {
+            let touch = seat.get_touch().expect("Seat is not dead");
+            evqh.register::<_, WaylandEnv>(&touch, self.my_id);
+            self.touch = Some(touch);
+        }
+        // destroy touch if applicable
+        if !capabilities.contains(wl_seat::Touch) {
+            if let Some(touch) = self.touch.take() {
+                touch.release();
+            }
+        }
     }
 }
 
@@ -387,11 +1489,13 @@ impl wl_pointer::Handler for WaylandEnv {
     fn enter(&mut self,
              _evqh: &mut EventQueueHandle,
              _proxy: &wl_pointer::WlPointer,
-             _serial: u32,
+             serial: u32,
              surface: &wl_surface::WlSurface,
              surface_x: f64,
              surface_y: f64)
     {
+        self.last_serial = serial;
+        self.pointer_serial = serial;
         self.mouse_location = (surface_x as i32, surface_y as i32);
         for &(ref window, ref eviter) in &self.windows {
             if window.equals(surface) {
@@ -403,6 +1507,7 @@ impl wl_pointer::Handler for WaylandEnv {
                 break;
             }
         }
+        self.update_cursor();
     }
 
     fn leave(&mut self,
@@ -440,11 +1545,15 @@ impl wl_pointer::Handler for WaylandEnv {
     fn button(&mut self,
               _evqh: &mut EventQueueHandle,
               _proxy: &wl_pointer::WlPointer,
-              _serial: u32,
+              serial: u32,
               _time: u32,
               button: u32,
               state: wl_pointer::ButtonState)
     {
+        self.last_serial = serial;
+        if let wl_pointer::ButtonState::Pressed = state {
+            self.button_serial = serial;
+        }
         if let Some(ref eviter) = self.mouse_focus {
             let state = match state {
                 wl_pointer::ButtonState::Pressed => ElementState::Pressed,
@@ -542,7 +1651,142 @@ impl wl_pointer::Handler for WaylandEnv {
     }
 }
 
-declare_handler!(WaylandEnv, wl_pointer::Handler, wl_pointer::WlPointer);
+impl zwp_relative_pointer_v1::Handler for WaylandEnv {
+    fn relative_motion(&mut self,
+                       _evqh: &mut EventQueueHandle,
+                       _proxy: &zwp_relative_pointer_v1::ZwpRelativePointerV1,
+                       _utime_hi: u32,
+                       _utime_lo: u32,
+                       dx: f64,
+                       dy: f64,
+                       _dx_unaccel: f64,
+                       _dy_unaccel: f64)
+    {
+        if let Some(ref eviter) = self.mouse_focus {
+            eviter.lock().unwrap().push_back(
+                Event::MouseMotion(dx, dy)
+            );
+        }
+    }
+}
+
+declare_handler!(WaylandEnv, zwp_relative_pointer_v1::Handler, zwp_relative_pointer_v1::ZwpRelativePointerV1);
+
+// Embedded windows share the connection of their host, and the events naming the surfaces
+// of the host can't be decoded by wayland-client. They are handled before that.
+unsafe impl Handler<wl_pointer::WlPointer> for WaylandEnv {
+    unsafe fn message(&mut self, evqh: &mut EventQueueHandle, proxy: &wl_pointer::WlPointer, opcode: u32,
+                      args: *const wl_argument) -> Result<(), ()>
+    {
+        // `enter` and `leave` name the surface after the serial
+        if (opcode == 0 || opcode == 1) && self.is_foreign_surface(object_argument(args, 1)) {
+            // the pointer is over the host, which gets the events
+            return Ok(());
+        }
+        <WaylandEnv as wl_pointer::Handler>::__message(self, evqh, proxy, opcode, args)
+    }
+}
+
+// Reads the object at position `index` in the arguments of an event.
+unsafe fn object_argument(args: *const wl_argument, index: isize) -> *mut wl_proxy {
+    *(args.offset(index) as *const *mut wl_proxy)
+}
+
+/*
+ * Touch Handling
+ */
+
+impl wl_touch::Handler for WaylandEnv {
+    fn down(&mut self,
+            _evqh: &mut EventQueueHandle,
+            _proxy: &wl_touch::WlTouch,
+            serial: u32,
+            _time: u32,
+            surface: &wl_surface::WlSurface,
+            id: i32,
+            x: f64,
+            y: f64)
+    {
+        self.last_serial = serial;
+        let eviter = match self.windows.iter().find(|&&(ref window, _)| window.equals(surface)) {
+            Some(&(_, ref eviter)) => eviter.clone(),
+            None => return
+        };
+        if !self.touch_windows.iter().any(|w| Arc::ptr_eq(w, &eviter)) {
+            return;
+        }
+        eviter.lock().unwrap().push_back(Event::Touch(Touch {
+            phase: TouchPhase::Started,
+            location: (x, y),
+            id: id as u64
+        }));
+        self.touch_points.push(TouchPoint { id: id, eviter: eviter, location: (x, y) });
+    }
+
+    fn up(&mut self,
+          _evqh: &mut EventQueueHandle,
+          _proxy: &wl_touch::WlTouch,
+          serial: u32,
+          _time: u32,
+          id: i32)
+    {
+        self.last_serial = serial;
+        // the compositor may reuse the id for the next finger
+        if let Some(index) = self.touch_points.iter().position(|p| p.id == id) {
+            let point = self.touch_points.remove(index);
+            point.eviter.lock().unwrap().push_back(Event::Touch(Touch {
+                phase: TouchPhase::Ended,
+                location: point.location,
+                id: id as u64
+            }));
+        }
+    }
+
+    fn motion(&mut self,
+              _evqh: &mut EventQueueHandle,
+              _proxy: &wl_touch::WlTouch,
+              _time: u32,
+              id: i32,
+              x: f64,
+              y: f64)
+    {
+        if let Some(point) = self.touch_points.iter_mut().find(|p| p.id == id) {
+            point.location = (x, y);
+            point.eviter.lock().unwrap().push_back(Event::Touch(Touch {
+                phase: TouchPhase::Moved,
+                location: (x, y),
+                id: id as u64
+            }));
+        }
+    }
+
+    fn cancel(&mut self,
+              _evqh: &mut EventQueueHandle,
+              _proxy: &wl_touch::WlTouch)
+    {
+        // the compositor took over the fingers, for example for a gesture
+        for point in self.touch_points.drain(..) {
+            point.eviter.lock().unwrap().push_back(Event::Touch(Touch {
+                phase: TouchPhase::Cancelled,
+                location: point.location,
+                id: point.id as u64
+            }));
+        }
+    }
+}
+
+unsafe impl Handler<wl_touch::WlTouch> for WaylandEnv {
+    unsafe fn message(&mut self, evqh: &mut EventQueueHandle, proxy: &wl_touch::WlTouch, opcode: u32,
+                      args: *const wl_argument) -> Result<(), ()>
+    {
+        // `down` names the surface after the serial and the time
+        if opcode == 0 && self.is_foreign_surface(object_argument(args, 2)) {
+            // the finger is on the host, which gets the events
+            return Ok(());
+        }
+        <WaylandEnv as wl_touch::Handler>::__message(self, evqh, proxy, opcode, args)
+    }
+}
 
 /*
  * Keyboard Handling
@@ -570,24 +1814,12 @@ impl wl_keyboard::Handler for WaylandEnv {
              surface: &wl_surface::WlSurface,
              keys: Vec<u8>)
     {
-        let mut opt_eviter = None;
-        for &(ref window, ref eviter) in &self.windows {
-            if window.equals(surface) {
-                opt_eviter = Some(eviter.clone());
-                break;
-            }
-        }
-        if let Some(ref eviter) = opt_eviter {
-            // send focused event
-            let mut guard = eviter.lock().unwrap();
-            guard.push_back(Event::Focused(true));
-        }
-        match self.kbd_handler {
-            KbdType::Mapped(ref mut h) => {
-                h.handler().target = opt_eviter;
-                h.enter(evqh, proxy, serial, surface, keys);
-            },
-            KbdType::Plain(ref mut opt) => { *opt = opt_eviter; }
+        self.last_serial = serial;
+        let opt_eviter = self.windows.iter().find(|&&(ref window, _)| window.equals(surface))
+                                            .map(|&(_, ref eviter)| eviter.clone());
+        self.focus_keyboard(opt_eviter);
+        if let KbdType::Mapped(ref mut h) = self.kbd_handler {
+            h.enter(evqh, proxy, serial, surface, keys);
         }
     }
 
@@ -597,18 +1829,10 @@ impl wl_keyboard::Handler for WaylandEnv {
              serial: u32,
              surface: &wl_surface::WlSurface)
     {
-        let opt_eviter = match self.kbd_handler {
-            KbdType::Mapped(ref mut h) => {
-                let eviter = h.handler().target.take();
-                h.leave(evqh, proxy, serial, surface);
-                eviter
-            },
-            KbdType::Plain(ref mut opt) => opt.take()
-        };
-        if let Some(eviter) = opt_eviter {
-            let mut guard = eviter.lock().unwrap();
-            guard.push_back(Event::Focused(false));
+        if let KbdType::Mapped(ref mut h) = self.kbd_handler {
+            h.leave(evqh, proxy, serial, surface);
         }
+        self.unfocus_keyboard();
     }
 
     fn key(&mut self,
@@ -619,6 +1843,7 @@ impl wl_keyboard::Handler for WaylandEnv {
            key: u32,
            state: wl_keyboard::KeyState)
     {
+        self.last_serial = serial;
         match self.kbd_handler {
             KbdType::Mapped(ref mut h) => h.key(evqh, proxy, serial, time, key, state),
             KbdType::Plain(Some(ref eviter)) => {
@@ -627,10 +1852,12 @@ impl wl_keyboard::Handler for WaylandEnv {
                     wl_keyboard::KeyState::Released => ElementState::Released,
                 };
                 let mut guard = eviter.lock().unwrap();
+                // without keymap, the keys that repeat are unknown
                 guard.push_back(Event::KeyboardInput(
                     state,
                     key as u8,
-                    None
+                    None,
+                    false
                 ));
             },
             KbdType::Plain(None) => ()
@@ -666,4 +1893,423 @@ impl wl_keyboard::Handler for WaylandEnv {
     }
 }
 
-declare_handler!(WaylandEnv, wl_keyboard::Handler, wl_keyboard::WlKeyboard);
+unsafe impl Handler<wl_keyboard::WlKeyboard> for WaylandEnv {
+    unsafe fn message(&mut self, evqh: &mut EventQueueHandle, proxy: &wl_keyboard::WlKeyboard, opcode: u32,
+                      args: *const wl_argument) -> Result<(), ()>
+    {
+        // `enter` and `leave` name the surface after the serial
+        if (opcode == 1 || opcode == 2) && self.is_foreign_surface(object_argument(args, 1)) {
+            if opcode == 1 {
+                // an embedded window gets the keyboard while its parent has it
+                let surface = object_argument(args, 1);
+                self.last_serial = *(args as *const u32);
+                let opt_eviter = self.parents.iter().find(|p| p.surface == surface).map(|p| p.eviter.clone());
+                self.focus_keyboard(opt_eviter);
+            } else {
+                self.unfocus_keyboard();
+            }
+            return Ok(());
+        }
+        <WaylandEnv as wl_keyboard::Handler>::__message(self, evqh, proxy, opcode, args)
+    }
+}
+
+impl WaylandEnv {
+    // Sends the keyboard events to the window of `opt_eviter`, if any.
+    fn focus_keyboard(&mut self, opt_eviter: Option<Arc<Mutex<VecDeque<Event>>>>) {
+        if let Some(ref eviter) = opt_eviter {
+            eviter.lock().unwrap().push_back(Event::Focused(true));
+        }
+        match self.kbd_handler {
+            KbdType::Mapped(ref mut h) => { h.handler().target = opt_eviter; },
+            KbdType::Plain(ref mut opt) => { *opt = opt_eviter; }
+        }
+    }
+
+    fn unfocus_keyboard(&mut self) {
+        let opt_eviter = match self.kbd_handler {
+            KbdType::Mapped(ref mut h) => {
+                h.handler().stop_repeat();
+                h.handler().target.take()
+            },
+            KbdType::Plain(ref mut opt) => opt.take()
+        };
+        if let Some(eviter) = opt_eviter {
+            eviter.lock().unwrap().push_back(Event::Focused(false));
+        }
+    }
+}
+
+/*
+ * Data device handling
+ */
+
+impl wl_data_device::Handler for WaylandEnv {
+    fn data_offer(&mut self,
+                  evqh: &mut EventQueueHandle,
+                  _proxy: &wl_data_device::WlDataDevice,
+                  id: wl_data_offer::WlDataOffer)
+    {
+        // the mime types of the offer are announced right after this event
+        evqh.register::<_, WaylandEnv>(&id, self.my_id);
+        self.data_offers.push((id, Vec::new()));
+    }
+
+    fn selection(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 _proxy: &wl_data_device::WlDataDevice,
+                 id: Option<&wl_data_offer::WlDataOffer>)
+    {
+        if let Some((old_offer, _)) = self.selection_offer.take() {
+            old_offer.destroy();
+        }
+        if let Some(id) = id {
+            if let Some(index) = self.data_offers.iter().position(|&(ref offer, _)| offer.equals(id)) {
+                self.selection_offer = Some(self.data_offers.remove(index));
+            }
+        }
+    }
+
+    fn enter(&mut self,
+             _evqh: &mut EventQueueHandle,
+             _proxy: &wl_data_device::WlDataDevice,
+             serial: u32,
+             surface: &wl_surface::WlSurface,
+             x: f64,
+             y: f64,
+             id: Option<&wl_data_offer::WlDataOffer>)
+    {
+        if let Some(drag) = self.drag_offer.take() {
+            drag.offer.destroy();
+        }
+
+        let (offer, mime_types) = match id.and_then(|id| {
+            self.data_offers.iter().position(|&(ref offer, _)| offer.equals(id))
+        }) {
+            Some(index) => self.data_offers.remove(index),
+            None => return
+        };
+        let eviter = match self.windows.iter().find(|&&(ref window, _)| window.equals(surface)) {
+            Some(&(_, ref eviter)) => eviter.clone(),
+            None => {
+                offer.destroy();
+                return
+            }
+        };
+
+        let has_uri_list = mime_types.iter().any(|m| m == "text/uri-list");
+        let mut receiver = None;
+        let mut paths = None;
+        if has_uri_list {
+            offer.accept(serial, Some("text/uri-list".into()));
+            if offer.version() >= 3 {
+                let copy = wl_data_device_manager::Copy.to_raw();
+                offer.set_actions(copy, copy);
+            }
+
+            let own_contents = self.drag_source.as_ref().and_then(|drag| {
+                drag.contents.iter().find(|c| c.mime_type == "text/uri-list")
+            });
+            if let Some(content) = own_contents {
+                // we can't answer our own request while waiting for the answer
+                paths = Some(uri_list::parse_uri_list(&content.data));
+            } else {
+                let mut fds = [0; 2];
+                if unsafe { libc::pipe(fds.as_mut_ptr()) } == 0 {
+                    offer.receive("text/uri-list".into(), fds[1]);
+                    unsafe { libc::close(fds[1]) };
+                    // the events keep being dispatched while the source writes
+                    set_nonblocking(fds[0]);
+                    receiver = Some(unsafe { File::from_raw_fd(fds[0]) });
+                }
+            }
+        } else {
+            offer.accept(serial, None);
+        }
+
+        let drag = DragOffer {
+            offer: offer,
+            eviter: eviter,
+            has_uri_list: has_uri_list,
+            receiver: receiver,
+            received: Vec::new(),
+            paths: paths,
+            location: (x as i32, y as i32),
+            dropped: false
+        };
+        drag.send_hovered();
+        self.drag_offer = Some(drag);
+    }
+
+    fn leave(&mut self,
+             _evqh: &mut EventQueueHandle,
+             _proxy: &wl_data_device::WlDataDevice)
+    {
+        // some compositors send this event after a drop, which is finished later on
+        let left = match self.drag_offer {
+            Some(ref drag) => !drag.dropped,
+            None => false
+        };
+        if left {
+            let drag = self.drag_offer.take().unwrap();
+            if drag.has_uri_list {
+                drag.eviter.lock().unwrap().push_back(Event::HoveredFileCancelled);
+            }
+            drag.offer.destroy();
+        }
+    }
+
+    fn motion(&mut self,
+              _evqh: &mut EventQueueHandle,
+              _proxy: &wl_data_device::WlDataDevice,
+              _time: u32,
+              x: f64,
+              y: f64)
+    {
+        if let Some(ref mut drag) = self.drag_offer {
+            drag.location = (x as i32, y as i32);
+            drag.send_hovered();
+        }
+    }
+
+    fn drop(&mut self,
+            _evqh: &mut EventQueueHandle,
+            _proxy: &wl_data_device::WlDataDevice)
+    {
+        let accepted = match self.drag_offer {
+            Some(ref drag) => drag.has_uri_list,
+            None => false
+        };
+        if accepted {
+            if let Some(ref mut drag) = self.drag_offer {
+                drag.dropped = true;
+            }
+        } else if let Some(drag) = self.drag_offer.take() {
+            drag.offer.destroy();
+        }
+
+        // while one of our windows drags, this drop is its own, whose data is read without the
+        // source, so before version 3 nothing else tells that it was dropped
+        if self.drag_source.as_ref().map_or(false, |drag| drag.source.version() < 3) {
+            self.finish_drag(accepted);
+        }
+    }
+}
+
+unsafe impl Handler<wl_data_device::WlDataDevice> for WaylandEnv {
+    unsafe fn message(&mut self, evqh: &mut EventQueueHandle, proxy: &wl_data_device::WlDataDevice, opcode: u32,
+                      args: *const wl_argument) -> Result<(), ()>
+    {
+        // `enter` names the surface after the serial, and the offer last
+        if opcode == 1 && self.is_foreign_surface(object_argument(args, 1)) {
+            // the drag is over the host, which handles it
+            let offer = object_argument(args, 4);
+            if let Some(index) = self.data_offers.iter().position(|&(ref o, _)| o.ptr() == offer) {
+                self.data_offers.remove(index).0.destroy();
+            }
+            return Ok(());
+        }
+        <WaylandEnv as wl_data_device::Handler>::__message(self, evqh, proxy, opcode, args)
+    }
+}
+
+impl wl_data_offer::Handler for WaylandEnv {
+    fn offer(&mut self,
+             _evqh: &mut EventQueueHandle,
+             proxy: &wl_data_offer::WlDataOffer,
+             mime_type: String)
+    {
+        for &mut (ref offer, ref mut mime_types) in &mut self.data_offers {
+            if offer.equals(proxy) {
+                mime_types.push(mime_type);
+                break;
+            }
+        }
+    }
+}
+
+declare_handler!(WaylandEnv, wl_data_offer::Handler, wl_data_offer::WlDataOffer);
+
+impl wl_data_source::Handler for WaylandEnv {
+    fn send(&mut self,
+            _evqh: &mut EventQueueHandle,
+            proxy: &wl_data_source::WlDataSource,
+            mime_type: String,
+            fd: ::std::os::unix::io::RawFd)
+    {
+        // the file is closed when dropped, which tells the receiver that all the data was sent
+        let file = unsafe { File::from_raw_fd(fd) };
+        let data = {
+            let contents = match (&self.selection_source, &self.drag_source) {
+                (&Some((ref source, ref contents)), _) if source.equals(proxy) => contents,
+                (_, &Some(ref drag)) if drag.source.equals(proxy) => &drag.contents,
+                _ => return
+            };
+            contents.iter().find(|c| c.mime_type == mime_type).map(|content| content.data.clone())
+        };
+        if let Some(data) = data {
+            self.send_data(file, data);
+        }
+
+        // before version 3, `dnd_finished` isn't sent, the data being read means it was dropped
+        let dropped = match self.drag_source {
+            Some(ref drag) => drag.source.equals(proxy) && proxy.version() < 3,
+            None => false
+        };
+        if dropped {
+            self.finish_drag(true);
+        }
+    }
+
+    fn action(&mut self,
+              _evqh: &mut EventQueueHandle,
+              proxy: &wl_data_source::WlDataSource,
+              dnd_action: u32)
+    {
+        if let Some(ref mut drag) = self.drag_source {
+            if drag.source.equals(proxy) {
+                let action = wl_data_device_manager::DndAction::from_bits_truncate(dnd_action);
+                drag.action = if action.contains(wl_data_device_manager::Move) {
+                    Some(DragAction::Move)
+                } else if action.contains(wl_data_device_manager::Copy) {
+                    Some(DragAction::Copy)
+                } else {
+                    None
+                };
+            }
+        }
+    }
+
+    fn dnd_finished(&mut self,
+                    _evqh: &mut EventQueueHandle,
+                    proxy: &wl_data_source::WlDataSource)
+    {
+        let finished = match self.drag_source {
+            Some(ref drag) => drag.source.equals(proxy),
+            None => false
+        };
+        if finished {
+            self.finish_drag(true);
+        } else {
+            proxy.destroy();
+        }
+    }
+
+    fn cancelled(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 proxy: &wl_data_source::WlDataSource)
+    {
+        // another client took the selection over
+        let cancelled = match self.selection_source {
+            Some((ref source, _)) => source.equals(proxy),
+            None => false
+        };
+        if cancelled {
+            self.selection_source = None;
+        }
+        // or the drag ended without the data being accepted
+        let cancelled = match self.drag_source {
+            Some(ref drag) => drag.source.equals(proxy),
+            None => false
+        };
+        if cancelled {
+            self.finish_drag(false);
+        } else {
+            proxy.destroy();
+        }
+    }
+}
+
+declare_handler!(WaylandEnv, wl_data_source::Handler, wl_data_source::WlDataSource);
+
+/*
+ * Primary selection handling
+ */
+
+impl zwp_primary_selection_device_v1::Handler for WaylandEnv {
+    fn data_offer(&mut self,
+                  evqh: &mut EventQueueHandle,
+                  _proxy: &zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1,
+                  offer: zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1)
+    {
+        // the mime types of the offer are announced right after this event
+        evqh.register::<_, WaylandEnv>(&offer, self.my_id);
+        self.primary_offers.push((offer, Vec::new()));
+    }
+
+    fn selection(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 _proxy: &zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1,
+                 id: Option<&zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1>)
+    {
+        if let Some((old_offer, _)) = self.primary_offer.take() {
+            old_offer.destroy();
+        }
+        if let Some(id) = id {
+            if let Some(index) = self.primary_offers.iter().position(|&(ref offer, _)| offer.equals(id)) {
+                self.primary_offer = Some(self.primary_offers.remove(index));
+            }
+        }
+    }
+}
+
+declare_handler!(WaylandEnv, zwp_primary_selection_device_v1::Handler,
+                 zwp_primary_selection_device_v1::ZwpPrimarySelectionDeviceV1);
+
+impl zwp_primary_selection_offer_v1::Handler for WaylandEnv {
+    fn offer(&mut self,
+             _evqh: &mut EventQueueHandle,
+             proxy: &zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1,
+             mime_type: String)
+    {
+        for &mut (ref offer, ref mut mime_types) in &mut self.primary_offers {
+            if offer.equals(proxy) {
+                mime_types.push(mime_type);
+                break;
+            }
+        }
+    }
+}
+
+declare_handler!(WaylandEnv, zwp_primary_selection_offer_v1::Handler,
+                 zwp_primary_selection_offer_v1::ZwpPrimarySelectionOfferV1);
+
+impl zwp_primary_selection_source_v1::Handler for WaylandEnv {
+    fn send(&mut self,
+            _evqh: &mut EventQueueHandle,
+            proxy: &zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1,
+            mime_type: String,
+            fd: ::std::os::unix::io::RawFd)
+    {
+        // the file is closed when dropped, which tells the receiver that all the data was sent
+        let file = unsafe { File::from_raw_fd(fd) };
+        let data = {
+            let contents = match self.primary_source {
+                Some((ref source, ref contents)) if source.equals(proxy) => contents,
+                _ => return
+            };
+            contents.iter().find(|c| c.mime_type == mime_type).map(|content| content.data.clone())
+        };
+        if let Some(data) = data {
+            self.send_data(file, data);
+        }
+    }
+
+    fn cancelled(&mut self,
+                 _evqh: &mut EventQueueHandle,
+                 proxy: &zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1)
+    {
+        // another client took the selection over
+        let cancelled = match self.primary_source {
+            Some((ref source, _)) => source.equals(proxy),
+            None => false
+        };
+        if cancelled {
+            self.primary_source = None;
+        }
+        proxy.destroy();
+    }
+}
+
+declare_handler!(WaylandEnv, zwp_primary_selection_source_v1::Handler,
+                 zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1);
@@ -0,0 +1,161 @@
+use std::env;
+
+use {CustomCursor, MouseCursor};
+use api::images::{premultiplied_argb, CursorCache};
+
+use super::software::ShmBuffer;
+
+use wayland_client::Proxy;
+use wayland_client::cursor::{is_available, load_theme, CursorTheme};
+use wayland_client::protocol::{wl_pointer, wl_shm, wl_surface};
+
+// Used when XCURSOR_SIZE isn't set, like libXcursor does
+const DEFAULT_CURSOR_SIZE: u32 = 24;
+
+// The cursor chosen by a window
+#[derive(Clone)]
+pub enum WindowCursor {
+    Themed(MouseCursor),
+    Custom(CustomCursor)
+}
+
+// Compositors leave the image of the pointer over a surface to the client, which draws it from
+// the XCursor theme of the user or from the images of the application
+pub struct PointerCursor {
+    surface: wl_surface::WlSurface,
+    // the theme is loaded on first use, with images for the scale it was loaded for
+    theme: Option<(CursorTheme, i32)>,
+    // the cursor attached to the surface stays in the cache, as it is the last one used
+    custom_cursors: CursorCache<(ShmBuffer, (u32, u32))>
+}
+
+impl PointerCursor {
+    pub fn new(surface: wl_surface::WlSurface) -> PointerCursor {
+        PointerCursor {
+            surface: surface,
+            theme: None,
+            custom_cursors: CursorCache::new()
+        }
+    }
+
+    // Shows `cursor` as the image of `pointer`, `serial` being the one of the last enter event
+    pub fn set_cursor(&mut self, pointer: &wl_pointer::WlPointer, serial: u32, cursor: &WindowCursor,
+                      scale: i32, shm: &wl_shm::WlShm)
+    {
+        match *cursor {
+            WindowCursor::Themed(cursor) => self.set_themed_cursor(pointer, serial, cursor, scale, shm),
+            WindowCursor::Custom(ref cursor) => self.set_custom_cursor(pointer, serial, cursor, shm)
+        }
+    }
+
+    fn set_custom_cursor(&mut self, pointer: &wl_pointer::WlPointer, serial: u32, cursor: &CustomCursor,
+                         shm: &wl_shm::WlShm)
+    {
+        let &(ref buffer, size) = self.custom_cursors.get(cursor, || {
+            let mut buffer = ShmBuffer::with_shm(shm, cursor.width, cursor.height, true);
+            buffer.write_argb(&premultiplied_argb(&cursor.rgba, cursor.width, cursor.height));
+            (buffer, (cursor.width, cursor.height))
+        }, drop);
+
+        // the images have one pixel per point of the surface
+        if self.surface.version() >= 3 {
+            self.surface.set_buffer_scale(1);
+        }
+        self.surface.attach(Some(buffer.get_buffer()), 0, 0);
+        self.surface.damage(0, 0, size.0 as i32, size.1 as i32);
+        self.surface.commit();
+        pointer.set_cursor(serial, Some(&self.surface), cursor.hotspot.0 as i32, cursor.hotspot.1 as i32);
+    }
+
+    fn set_themed_cursor(&mut self, pointer: &wl_pointer::WlPointer, serial: u32, cursor: MouseCursor,
+                         scale: i32, shm: &wl_shm::WlShm)
+    {
+        if let MouseCursor::NoneCursor = cursor {
+            pointer.set_cursor(serial, None, 0, 0);
+            return;
+        }
+        if !is_available() {
+            // libwayland-cursor is missing, the compositor keeps showing its own cursor
+            return;
+        }
+        // buffer scales need version 3 of the surface
+        let scale = if self.surface.version() >= 3 { scale } else { 1 };
+        if self.theme.as_ref().map(|&(_, s)| s != scale).unwrap_or(true) {
+            let name = env::var("XCURSOR_THEME").ok();
+            let size = env::var("XCURSOR_SIZE").ok().and_then(|s| s.parse().ok())
+                                                .unwrap_or(DEFAULT_CURSOR_SIZE);
+            let theme = load_theme(name.as_ref().map(|s| &s[..]), size * scale as u32, shm);
+            self.theme = Some((theme, scale));
+        }
+        let theme = &self.theme.as_ref().unwrap().0;
+
+        let image = cursor_names(cursor).iter().chain(&["left_ptr", "default"])
+                                        .filter_map(|name| theme.get_cursor(name)).next();
+        let image = match image {
+            Some(image) => image,
+            None => return
+        };
+        // animated cursors only show their first frame
+        let (buffer, (w, h, hx, hy, _)) = match (image.frame_buffer(0), image.frame_info(0)) {
+            (Some(buffer), Some(info)) => (buffer, info),
+            _ => return
+        };
+
+        if self.surface.version() >= 3 {
+            self.surface.set_buffer_scale(scale);
+        }
+        self.surface.attach(Some(&buffer), 0, 0);
+        self.surface.damage(0, 0, w as i32 / scale, h as i32 / scale);
+        self.surface.commit();
+        pointer.set_cursor(serial, Some(&self.surface), hx as i32 / scale, hy as i32 / scale);
+    }
+}
+
+impl Drop for PointerCursor {
+    fn drop(&mut self) {
+        self.surface.destroy();
+    }
+}
+
+// The names of the images of `cursor` in XCursor themes, the better looking ones first. They
+// differ between themes, which either follow X11 or CSS.
+fn cursor_names(cursor: MouseCursor) -> &'static [&'static str] {
+    match cursor {
+        MouseCursor::Default => &["left_ptr", "default"],
+        MouseCursor::Crosshair => &["crosshair", "cross"],
+        MouseCursor::Hand => &["hand2", "hand1", "pointer"],
+        MouseCursor::Arrow => &["arrow", "left_ptr"],
+        MouseCursor::Move => &["move", "fleur"],
+        MouseCursor::Text => &["text", "xterm"],
+        MouseCursor::Wait => &["watch", "wait"],
+        MouseCursor::Help => &["question_arrow", "help"],
+        MouseCursor::Progress => &["left_ptr_watch", "progress"],
+        MouseCursor::NotAllowed => &["crossed_circle", "not-allowed"],
+        MouseCursor::ContextMenu => &["context-menu"],
+        MouseCursor::NoneCursor => &[],
+        MouseCursor::Cell => &["plus", "cell"],
+        MouseCursor::VerticalText => &["vertical-text"],
+        MouseCursor::Alias => &["link", "alias"],
+        MouseCursor::Copy => &["copy"],
+        MouseCursor::NoDrop => &["no-drop", "circle"],
+        MouseCursor::Grab => &["openhand", "grab"],
+        MouseCursor::Grabbing => &["closedhand", "grabbing"],
+        MouseCursor::AllScroll => &["all-scroll", "fleur"],
+        MouseCursor::ZoomIn => &["zoom-in"],
+        MouseCursor::ZoomOut => &["zoom-out"],
+        MouseCursor::EResize => &["right_side", "e-resize"],
+        MouseCursor::NResize => &["top_side", "n-resize"],
+        MouseCursor::NeResize => &["top_right_corner", "ne-resize"],
+        MouseCursor::NwResize => &["top_left_corner", "nw-resize"],
+        MouseCursor::SResize => &["bottom_side", "s-resize"],
+        MouseCursor::SeResize => &["bottom_right_corner", "se-resize"],
+        MouseCursor::SwResize => &["bottom_left_corner", "sw-resize"],
+        MouseCursor::WResize => &["left_side", "w-resize"],
+        MouseCursor::EwResize => &["h_double_arrow", "ew-resize"],
+        MouseCursor::NsResize => &["v_double_arrow", "ns-resize"],
+        MouseCursor::NeswResize => &["fd_double_arrow", "size_fdiag", "nesw-resize"],
+        MouseCursor::NwseResize => &["bd_double_arrow", "size_bdiag", "nwse-resize"],
+        MouseCursor::ColResize => &["split_h", "h_double_arrow", "col-resize"],
+        MouseCursor::RowResize => &["split_v", "v_double_arrow", "row-resize"],
+    }
+}
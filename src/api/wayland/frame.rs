@@ -0,0 +1,257 @@
+//! The borders drawn around xdg_shell windows whose decorations are left to us, which look like
+//! the ones `wayland-window` draws around wl_shell windows.
+//!
+//! Each border is a subsurface of the window. The pointer gets its own object, whose events are
+//! only about the borders, to move and resize the window.
+
+use std::cmp::max;
+
+use wayland_client::Proxy;
+use wayland_client::protocol::{wl_pointer, wl_seat, wl_shm, wl_subsurface, wl_surface};
+
+use MouseCursor;
+
+use super::cursor::{PointerCursor, WindowCursor};
+use super::software::ShmBuffer;
+use super::xdg_shell::xdg_toplevel::ResizeEdge;
+
+// the size of the borders around the window, the top one holding the title bar
+pub const BORDER_SIZE: i32 = 8;
+pub const TITLE_BAR_SIZE: i32 = 24;
+
+// a dark gray
+const BORDER_COLOR: u32 = 0xFF444444;
+
+// indices of the borders
+const TOP: usize = 0;
+const RIGHT: usize = 1;
+const BOTTOM: usize = 2;
+const LEFT: usize = 3;
+
+/// What a press of the left button on the borders does.
+pub enum FrameAction {
+    Move,
+    Resize(ResizeEdge),
+}
+
+pub struct Frame {
+    surfaces: Vec<wl_surface::WlSurface>,
+    subsurfaces: Vec<wl_subsurface::WlSubsurface>,
+    // the buffers attached to the borders, replaced when the window is resized
+    buffers: Vec<ShmBuffer>,
+    shm: wl_shm::WlShm,
+    seat: Option<wl_seat::WlSeat>,
+    pointer: Option<wl_pointer::WlPointer>,
+    cursor: PointerCursor,
+    // the scale of the images of the cursor
+    scale: i32,
+    // the border under the pointer, and the location of the pointer on it
+    location: Option<(usize, f64, f64)>,
+    // serial of the last time the pointer entered a border
+    pointer_serial: u32,
+    size: (u32, u32),
+    visible: bool,
+}
+
+impl Frame {
+    /// Creates hidden borders around `surface`, of the size `size` without them.
+    pub fn new(surfaces: Vec<wl_surface::WlSurface>, subsurfaces: Vec<wl_subsurface::WlSubsurface>,
+               shm: wl_shm::WlShm, seat: Option<wl_seat::WlSeat>, cursor: PointerCursor, scale: i32,
+               size: (u32, u32)) -> Frame
+    {
+        // the borders are moved along with the window, without waiting for it to be drawn
+        for subsurface in &subsurfaces {
+            subsurface.set_desync();
+        }
+        let pointer = seat.as_ref().map(|seat| seat.get_pointer().expect("Seat cannot be dead"));
+        Frame {
+            surfaces: surfaces,
+            subsurfaces: subsurfaces,
+            buffers: Vec::new(),
+            shm: shm,
+            seat: seat,
+            pointer: pointer,
+            cursor: cursor,
+            scale: scale,
+            location: None,
+            pointer_serial: 0,
+            size: size,
+            visible: false,
+        }
+    }
+
+    #[inline]
+    pub fn pointer(&self) -> Option<&wl_pointer::WlPointer> {
+        self.pointer.as_ref()
+    }
+
+    #[inline]
+    pub fn seat(&self) -> Option<&wl_seat::WlSeat> {
+        self.seat.as_ref()
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    pub fn set_visible(&mut self, visible: bool) {
+        if self.visible != visible {
+            self.visible = visible;
+            self.draw();
+        }
+    }
+
+    /// Resizes the borders around a window of size `(width, height)` without them.
+    pub fn resize(&mut self, size: (u32, u32)) {
+        if self.size != size {
+            self.size = size;
+            self.draw();
+        }
+    }
+
+    /// Adds the size of the borders to the size of the window, if they are shown.
+    pub fn add_borders(&self, (width, height): (u32, u32)) -> (u32, u32) {
+        if !self.visible {
+            return (width, height);
+        }
+        (width + 2 * BORDER_SIZE as u32, height + (BORDER_SIZE + TITLE_BAR_SIZE) as u32)
+    }
+
+    /// Subtracts the size of the borders from a size suggested for the window with them, if
+    /// they are shown. A size of 0 is left as it is, since it lets us pick the size.
+    pub fn subtract_borders(&self, (width, height): (i32, i32)) -> (i32, i32) {
+        if !self.visible || width <= 0 || height <= 0 {
+            return (width, height);
+        }
+        (max(width - 2 * BORDER_SIZE, 1), max(height - BORDER_SIZE - TITLE_BAR_SIZE, 1))
+    }
+
+    /// The part of the window and its borders that the compositor treats as the window, relative
+    /// to the window.
+    pub fn geometry(&self) -> (i32, i32, i32, i32) {
+        let (width, height) = self.add_borders(self.size);
+        if self.visible {
+            (-BORDER_SIZE, -TITLE_BAR_SIZE, width as i32, height as i32)
+        } else {
+            (0, 0, width as i32, height as i32)
+        }
+    }
+
+    fn draw(&mut self) {
+        if !self.visible {
+            for surface in &self.surfaces {
+                surface.attach(None, 0, 0);
+                surface.commit();
+            }
+            self.buffers.clear();
+            return;
+        }
+
+        let (width, height) = (self.size.0 as i32, self.size.1 as i32);
+        let borders = [
+            (TOP, -BORDER_SIZE, -TITLE_BAR_SIZE, width + 2 * BORDER_SIZE, TITLE_BAR_SIZE),
+            (RIGHT, width, 0, BORDER_SIZE, height),
+            (BOTTOM, -BORDER_SIZE, height, width + 2 * BORDER_SIZE, BORDER_SIZE),
+            (LEFT, -BORDER_SIZE, 0, BORDER_SIZE, height),
+        ];
+        let mut buffers = Vec::with_capacity(borders.len());
+        for &(index, x, y, w, h) in &borders {
+            let mut buffer = ShmBuffer::with_shm(&self.shm, w as u32, h as u32, false);
+            buffer.write_argb(&vec![BORDER_COLOR; (w * h) as usize]);
+            let surface = &self.surfaces[index];
+            surface.attach(Some(buffer.get_buffer()), 0, 0);
+            surface.damage(0, 0, w, h);
+            surface.commit();
+            self.subsurfaces[index].set_position(x, y);
+            buffers.push(buffer);
+        }
+        // the previous buffers were replaced by the new ones
+        self.buffers = buffers;
+    }
+
+    pub fn pointer_entered(&mut self, surface: &wl_surface::WlSurface, serial: u32, x: f64, y: f64) {
+        self.pointer_serial = serial;
+        self.location = self.surfaces.iter().position(|s| s.equals(surface)).map(|index| (index, x, y));
+        self.update_cursor();
+    }
+
+    pub fn pointer_left(&mut self) {
+        self.location = None;
+    }
+
+    pub fn pointer_moved(&mut self, x: f64, y: f64) {
+        if let Some((index, _, _)) = self.location {
+            self.location = Some((index, x, y));
+            self.update_cursor();
+        }
+    }
+
+    /// Returns what a press of the left button does where the pointer is.
+    pub fn action(&self) -> Option<FrameAction> {
+        let (index, x, y) = match self.location {
+            Some(location) => location,
+            None => return None
+        };
+        let (left, right) = (x < BORDER_SIZE as f64, x >= (self.size.0 as i32 + BORDER_SIZE) as f64);
+        let edge = match index {
+            TOP if y < BORDER_SIZE as f64 && left => ResizeEdge::TopLeft,
+            TOP if y < BORDER_SIZE as f64 && right => ResizeEdge::TopRight,
+            TOP if y < BORDER_SIZE as f64 => ResizeEdge::Top,
+            // the title bar
+            TOP if left => ResizeEdge::Left,
+            TOP if right => ResizeEdge::Right,
+            TOP => return Some(FrameAction::Move),
+            BOTTOM if left => ResizeEdge::BottomLeft,
+            BOTTOM if right => ResizeEdge::BottomRight,
+            BOTTOM => ResizeEdge::Bottom,
+            LEFT => ResizeEdge::Left,
+            _ => ResizeEdge::Right,
+        };
+        Some(FrameAction::Resize(edge))
+    }
+
+    fn update_cursor(&mut self) {
+        // the cursor over the window is set by the context
+        if self.location.is_none() {
+            return;
+        }
+        let cursor = match self.action() {
+            Some(FrameAction::Resize(edge)) => match edge {
+                ResizeEdge::Top => MouseCursor::NResize,
+                ResizeEdge::Bottom => MouseCursor::SResize,
+                ResizeEdge::Left => MouseCursor::WResize,
+                ResizeEdge::Right => MouseCursor::EResize,
+                ResizeEdge::TopLeft => MouseCursor::NwResize,
+                ResizeEdge::TopRight => MouseCursor::NeResize,
+                ResizeEdge::BottomLeft => MouseCursor::SwResize,
+                ResizeEdge::BottomRight => MouseCursor::SeResize,
+                ResizeEdge::None => MouseCursor::Default,
+            },
+            _ => MouseCursor::Default
+        };
+        if let Some(ref pointer) = self.pointer {
+            self.cursor.set_cursor(pointer, self.pointer_serial, &WindowCursor::Themed(cursor),
+                                   self.scale, &self.shm);
+        }
+    }
+}
+
+impl Drop for Frame {
+    fn drop(&mut self) {
+        self.buffers.clear();
+        for subsurface in &self.subsurfaces {
+            subsurface.destroy();
+        }
+        for surface in &self.surfaces {
+            surface.destroy();
+        }
+        // the seat is bound with version 5, which its pointer shares
+        if let Some(ref pointer) = self.pointer {
+            pointer.release();
+        }
+        if let Some(ref seat) = self.seat {
+            seat.release();
+        }
+    }
+}
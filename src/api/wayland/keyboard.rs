@@ -1,5 +1,7 @@
+use std::cmp::max;
 use std::collections::VecDeque;
 use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use {VirtualKeyCode, ElementState, Event};
 
@@ -7,13 +9,79 @@ use super::wayland_kbd;
 use wayland_client::EventQueueHandle;
 use wayland_client::protocol::wl_keyboard;
 
+// Used until the compositor sends its settings
+const DEFAULT_REPEAT_DELAY_MS: u64 = 600;
+const DEFAULT_REPEAT_RATE: u64 = 25;
+
 pub struct KbdHandler {
-    pub target: Option<Arc<Mutex<VecDeque<Event>>>>
+    pub target: Option<Arc<Mutex<VecDeque<Event>>>>,
+    // delay before the first repeat and interval between the next ones, `None` if keys don't
+    // repeat
+    repeat_timing: Option<(Duration, Duration)>,
+    // the key held down, and when it is next repeated
+    repeat: Option<(HeldKey, Instant)>
+}
+
+// Compositors leave the repeat of keys to the clients
+struct HeldKey {
+    rawkey: u32,
+    vkcode: Option<VirtualKeyCode>,
+    utf8: Option<String>
 }
 
 impl KbdHandler {
     pub fn new() -> KbdHandler {
-        KbdHandler { target: None }
+        KbdHandler {
+            target: None,
+            repeat_timing: Some((Duration::from_millis(DEFAULT_REPEAT_DELAY_MS),
+                                 Duration::from_millis(1000 / DEFAULT_REPEAT_RATE))),
+            repeat: None
+        }
+    }
+
+    /// Returns how long the event loop can wait before the held key is repeated.
+    pub fn repeat_timeout(&self) -> Option<Duration> {
+        self.repeat.as_ref().map(|&(_, next)| {
+            let now = Instant::now();
+            if next > now { next - now } else { Duration::from_millis(0) }
+        })
+    }
+
+    /// Sends the repeat of the held key to the focused window, if it is due.
+    pub fn poll_repeat(&mut self) {
+        let interval = match self.repeat_timing {
+            Some((_, interval)) => interval,
+            None => return
+        };
+        let now = Instant::now();
+        if let Some((ref key, ref mut next)) = self.repeat {
+            if *next > now {
+                return;
+            }
+            // a late event loop gets a single repeat rather than a burst of them
+            *next = now + interval;
+            if let Some(ref eviter) = self.target {
+                key.send(eviter, ElementState::Pressed, true);
+            }
+        }
+    }
+
+    pub fn stop_repeat(&mut self) {
+        self.repeat = None;
+    }
+}
+
+impl HeldKey {
+    fn send(&self, eviter: &Mutex<VecDeque<Event>>, state: ElementState, is_repeat: bool) {
+        let mut guard = eviter.lock().unwrap();
+        guard.push_back(Event::KeyboardInput(state, self.rawkey as u8, self.vkcode, is_repeat));
+        // send char event only on key press, not release
+        if let ElementState::Released = state { return }
+        if let Some(ref txt) = self.utf8 {
+            for chr in txt.chars() {
+                guard.push_back(Event::ReceivedCharacter(chr));
+            }
+        }
     }
 }
 
@@ -28,23 +96,66 @@ impl wayland_kbd::Handler for KbdHandler {
            state: wl_keyboard::KeyState,
            utf8: Option<String>)
     {
+        let state = match state {
+            wl_keyboard::KeyState::Pressed => ElementState::Pressed,
+            wl_keyboard::KeyState::Released => ElementState::Released,
+        };
+        let key = HeldKey {
+            rawkey: rawkey,
+            vkcode: key_to_vkey(rawkey, keysym),
+            utf8: utf8
+        };
         if let Some(ref eviter) = self.target {
-            let state = match state {
-                wl_keyboard::KeyState::Pressed => ElementState::Pressed,
-                wl_keyboard::KeyState::Released => ElementState::Released,
-            };
-            let vkcode = key_to_vkey(rawkey, keysym);
-            let mut guard = eviter.lock().unwrap();
-            guard.push_back(Event::KeyboardInput(state, rawkey as u8, vkcode));
-            // send char event only on key press, not release
-            if let ElementState::Released = state { return }
-            if let Some(txt) = utf8 {
-                for chr in txt.chars() {
-                    guard.push_back(Event::ReceivedCharacter(chr));
+            key.send(eviter, state, false);
+        }
+
+        match state {
+            ElementState::Pressed => {
+                if let Some((delay, _)) = self.repeat_timing {
+                    if key_repeats(keysym) {
+                        self.repeat = Some((key, Instant::now() + delay));
+                    }
+                }
+            },
+            ElementState::Released => {
+                let released = match self.repeat {
+                    Some((ref held, _)) => held.rawkey == rawkey,
+                    None => false
+                };
+                if released {
+                    self.repeat = None;
                 }
             }
         }
     }
+
+    fn repeat_info(&mut self,
+                   _evqh: &mut EventQueueHandle,
+                   _proxy: &wl_keyboard::WlKeyboard,
+                   rate: i32,
+                   delay: i32)
+    {
+        // a rate of 0 disables the repeat
+        self.repeat_timing = if rate > 0 {
+            Some((Duration::from_millis(max(delay, 0) as u64), Duration::from_millis(max(1000 / rate, 1) as u64)))
+        } else {
+            None
+        };
+        if self.repeat_timing.is_none() {
+            self.repeat = None;
+        }
+    }
+}
+
+// Modifiers and lock keys don't repeat
+fn key_repeats(keysym: u32) -> bool {
+    use super::wayland_kbd::keysyms;
+    match keysym {
+        keysyms::XKB_KEY_Shift_L ..= keysyms::XKB_KEY_Hyper_R => false,
+        keysyms::XKB_KEY_ISO_Lock ..= keysyms::XKB_KEY_ISO_Last_Group_Lock => false,
+        keysyms::XKB_KEY_Num_Lock | keysyms::XKB_KEY_Scroll_Lock | keysyms::XKB_KEY_Mode_switch => false,
+        _ => true
+    }
 }
 
 fn key_to_vkey(rawkey: u32, keysym: u32) -> Option<VirtualKeyCode> {
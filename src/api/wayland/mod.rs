@@ -8,5 +8,14 @@ extern crate wayland_kbd;
 extern crate wayland_window;
 
 mod context;
+mod cursor;
+mod frame;
 mod keyboard;
+mod pointer_constraints;
+mod primary_selection;
+mod relative_pointer;
+mod software;
 mod window;
+mod xdg_decoration;
+mod xdg_shell;
+mod xdg_toplevel_icon;
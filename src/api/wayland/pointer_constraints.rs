@@ -0,0 +1,25 @@
+//! The `pointer_constraints_unstable_v1` protocol, generated by the build script from
+//! `protocols/pointer-constraints-unstable-v1.xml`.
+
+pub use self::generated::client::*;
+
+mod generated {
+    // the generated code triggers a lot of warnings
+    #![allow(dead_code,non_camel_case_types,unused_unsafe,unused_variables)]
+    #![allow(non_upper_case_globals,non_snake_case,unused_imports)]
+    #![allow(missing_docs)]
+    #![allow(unknown_lints,static_mut_refs,unused_braces)]
+
+    pub mod interfaces {
+        #[doc(hidden)] pub use wayland_client::protocol_interfaces::{wl_pointer_interface, wl_region_interface,
+                                                                     wl_surface_interface};
+        include!(concat!(env!("OUT_DIR"), "/pointer_constraints_interfaces.rs"));
+    }
+
+    pub mod client {
+        #[doc(hidden)] pub use wayland_client::{Proxy, Handler, EventQueueHandle, RequestResult};
+        #[doc(hidden)] pub use super::interfaces;
+        #[doc(hidden)] pub use wayland_client::protocol::{wl_pointer, wl_region, wl_surface};
+        include!(concat!(env!("OUT_DIR"), "/pointer_constraints_api.rs"));
+    }
+}
@@ -0,0 +1,24 @@
+//! The `relative_pointer_unstable_v1` protocol, generated by the build script from
+//! `protocols/relative-pointer-unstable-v1.xml`.
+
+pub use self::generated::client::*;
+
+mod generated {
+    // the generated code triggers a lot of warnings
+    #![allow(dead_code,non_camel_case_types,unused_unsafe,unused_variables)]
+    #![allow(non_upper_case_globals,non_snake_case,unused_imports)]
+    #![allow(missing_docs)]
+    #![allow(unknown_lints,static_mut_refs,unused_braces)]
+
+    pub mod interfaces {
+        #[doc(hidden)] pub use wayland_client::protocol_interfaces::wl_pointer_interface;
+        include!(concat!(env!("OUT_DIR"), "/relative_pointer_interfaces.rs"));
+    }
+
+    pub mod client {
+        #[doc(hidden)] pub use wayland_client::{Proxy, Handler, EventQueueHandle, RequestResult};
+        #[doc(hidden)] pub use super::interfaces;
+        #[doc(hidden)] pub use wayland_client::protocol::wl_pointer;
+        include!(concat!(env!("OUT_DIR"), "/relative_pointer_api.rs"));
+    }
+}
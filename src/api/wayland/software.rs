@@ -0,0 +1,228 @@
+//! Presentation of pixels drawn on the CPU, through `wl_shm` buffers shared with the compositor.
+//!
+//! The compositor reads a buffer until it releases it, so two of them are used in turn.
+
+use std::{env, io, ptr, slice};
+use std::collections::VecDeque;
+use std::fs::{self, File, OpenOptions};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicUsize, ATOMIC_USIZE_INIT, Ordering};
+
+use libc;
+
+use wayland_client::{EventQueueHandle, Proxy};
+use wayland_client::protocol::{wl_buffer, wl_shm, wl_shm_pool, wl_surface};
+
+use {Event, PixelFormat, Rect};
+
+use super::WaylandContext;
+
+// A third buffer lets a frame be drawn while the compositor holds the one being shown and the
+// previous one. Frames presented while they are all held are skipped.
+const MAX_BUFFERS: usize = 3;
+
+/// A buffer in memory shared with the compositor.
+pub struct ShmBuffer {
+    // the memory is shared through this file
+    _file: File,
+    memory: *mut u8,
+    size: usize,
+    pool: wl_shm_pool::WlShmPool,
+    buffer: wl_buffer::WlBuffer,
+    width: u32,
+    height: u32,
+    // the compositor reads the buffer until it releases it
+    busy: bool,
+}
+
+// the mapping is only accessed through the buffer
+unsafe impl Send for ShmBuffer {}
+
+impl ShmBuffer {
+    pub fn new(ctxt: &WaylandContext, width: u32, height: u32, transparent: bool) -> ShmBuffer {
+        ShmBuffer::create(width, height, transparent, |fd, size| ctxt.create_shm_pool(fd, size))
+    }
+
+    /// Creates a buffer while the `WaylandContext` is busy, from within the event handlers.
+    pub fn with_shm(shm: &wl_shm::WlShm, width: u32, height: u32, transparent: bool) -> ShmBuffer {
+        ShmBuffer::create(width, height, transparent,
+                          |fd, size| shm.create_pool(fd, size).expect("Shm cannot be dead"))
+    }
+
+    fn create<F>(width: u32, height: u32, transparent: bool, create_pool: F) -> ShmBuffer
+        where F: FnOnce(RawFd, i32) -> wl_shm_pool::WlShmPool
+    {
+        let stride = width as usize * 4;
+        let size = stride * height as usize;
+        let file = create_shm_file(size).expect("Failed to create a shared memory buffer.");
+
+        let memory = unsafe {
+            libc::mmap(ptr::null_mut(), size, libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED,
+                       file.as_raw_fd(), 0)
+        };
+        assert!(memory != libc::MAP_FAILED, "Failed to map a shared memory buffer.");
+
+        let pool = create_pool(file.as_raw_fd(), size as i32);
+        // the alpha is ignored unless the window is transparent
+        let format = if transparent { wl_shm::Format::Argb8888 } else { wl_shm::Format::Xrgb8888 };
+        let buffer = pool.create_buffer(0, width as i32, height as i32, stride as i32, format)
+                         .expect("Shm pool cannot be dead");
+
+        ShmBuffer {
+            _file: file,
+            memory: memory as *mut u8,
+            size: size,
+            pool: pool,
+            buffer: buffer,
+            width: width,
+            height: height,
+            busy: false,
+        }
+    }
+
+    #[inline]
+    pub fn get_buffer(&self) -> &wl_buffer::WlBuffer {
+        &self.buffer
+    }
+
+    // Copies pixels that are already `wl_shm` values to the buffer.
+    pub fn write_argb(&mut self, pixels: &[u32]) {
+        let memory = unsafe { slice::from_raw_parts_mut(self.memory as *mut u32, self.size / 4) };
+        for (dest, &src) in memory.iter_mut().zip(pixels) {
+            *dest = src.to_le();
+        }
+    }
+
+    // Copies the pixels to the buffer, in the byte order of the `wl_shm` formats.
+    fn write(&mut self, pixels: &[u8], format: PixelFormat) {
+        let memory = unsafe { slice::from_raw_parts_mut(self.memory, self.size) };
+        match format {
+            PixelFormat::Bgra8 if cfg!(target_endian = "little") => {
+                memory.copy_from_slice(&pixels[.. self.size]);
+            },
+            _ => {
+                for (dest, src) in memory.chunks_mut(4).zip(pixels.chunks(4)) {
+                    let (r, g, b, a) = match format {
+                        PixelFormat::Rgba8 => (src[0], src[1], src[2], src[3]),
+                        PixelFormat::Bgra8 => (src[2], src[1], src[0], src[3]),
+                    };
+                    // the formats are little-endian 32 bits values
+                    let value = (a as u32) << 24 | (r as u32) << 16 | (g as u32) << 8 | b as u32;
+                    dest.copy_from_slice(&value.to_le_bytes());
+                }
+            },
+        }
+    }
+}
+
+impl Drop for ShmBuffer {
+    fn drop(&mut self) {
+        self.buffer.destroy();
+        self.pool.destroy();
+        unsafe { libc::munmap(self.memory as *mut libc::c_void, self.size) };
+    }
+}
+
+/// Holds the buffers of a window, and tracks which ones the compositor released.
+pub struct SoftwareHandler {
+    buffers: Vec<ShmBuffer>,
+    // size of the last buffer attached to the surface
+    last_size: Option<(u32, u32)>,
+    eviter: Arc<Mutex<VecDeque<Event>>>,
+    // a frame was skipped, which is redrawn once a buffer is released
+    skipped: bool,
+}
+
+impl SoftwareHandler {
+    pub fn new(eviter: Arc<Mutex<VecDeque<Event>>>) -> SoftwareHandler {
+        SoftwareHandler {
+            buffers: Vec::new(),
+            last_size: None,
+            eviter: eviter,
+            skipped: false,
+        }
+    }
+
+    /// Returns the index of a buffer of the given size that the compositor doesn't use.
+    ///
+    /// Unused buffers of another size are dropped, since the window was resized.
+    pub fn find_free_buffer(&mut self, width: u32, height: u32) -> Option<usize> {
+        self.buffers.retain(|b| b.busy || (b.width == width && b.height == height));
+        self.buffers.iter().position(|b| !b.busy)
+    }
+
+    /// Returns false if the compositor holds as many buffers as a window may have, in which case
+    /// the frame is skipped and an `Event::RedrawRequested` is sent once a buffer is released.
+    pub fn can_add_buffer(&mut self) -> bool {
+        if self.buffers.len() < MAX_BUFFERS {
+            true
+        } else {
+            self.skipped = true;
+            false
+        }
+    }
+
+    pub fn add_buffer(&mut self, buffer: ShmBuffer) -> usize {
+        self.buffers.push(buffer);
+        self.buffers.len() - 1
+    }
+
+    /// Copies the pixels to a buffer and attaches it to the surface.
+    pub fn present(&mut self, index: usize, surface: &wl_surface::WlSurface, pixels: &[u8],
+                   format: PixelFormat, damage: Option<&[Rect]>)
+    {
+        let buffer = &mut self.buffers[index];
+        buffer.write(pixels, format);
+        buffer.busy = true;
+
+        surface.attach(Some(&buffer.buffer), 0, 0);
+        let size = (buffer.width, buffer.height);
+        match damage {
+            // everything changed along with the size
+            Some(damage) if self.last_size == Some(size) => {
+                for rect in damage {
+                    surface.damage(rect.x, rect.y, rect.width as i32, rect.height as i32);
+                }
+            },
+            _ => { surface.damage(0, 0, size.0 as i32, size.1 as i32); },
+        }
+        surface.commit();
+        self.last_size = Some(size);
+    }
+}
+
+impl wl_buffer::Handler for SoftwareHandler {
+    fn release(&mut self, _evqh: &mut EventQueueHandle, proxy: &wl_buffer::WlBuffer) {
+        for buffer in &mut self.buffers {
+            if buffer.buffer.equals(proxy) {
+                buffer.busy = false;
+            }
+        }
+        if self.skipped {
+            self.skipped = false;
+            self.eviter.lock().unwrap().push_back(Event::RedrawRequested);
+        }
+    }
+}
+
+declare_handler!(SoftwareHandler, wl_buffer::Handler, wl_buffer::WlBuffer);
+
+// Creates a file of `size` bytes that only exists in memory, to be shared with the compositor.
+fn create_shm_file(size: usize) -> io::Result<File> {
+    static COUNTER: AtomicUsize = ATOMIC_USIZE_INIT;
+
+    let dir = match env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => return Err(io::Error::new(io::ErrorKind::NotFound, "XDG_RUNTIME_DIR is not set")),
+    };
+    let path = dir.join(format!("winit-shm-{}-{}", unsafe { libc::getpid() },
+                                COUNTER.fetch_add(1, Ordering::Relaxed)));
+
+    let file = try!(OpenOptions::new().read(true).write(true).create_new(true).open(&path));
+    // the file stays alive as long as it is open
+    try!(fs::remove_file(&path));
+    try!(file.set_len(size as u64));
+    Ok(file)
+}
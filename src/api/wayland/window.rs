@@ -1,15 +1,22 @@
 use std::collections::VecDeque;
+use std::mem;
 use std::sync::{Arc, Mutex};
 
 use wayland_client::{EventQueue, EventQueueHandle, Init};
-use wayland_client::protocol::{wl_display,wl_surface,wl_shell_surface};
+use wayland_client::protocol::{wl_display,wl_surface,wl_shell_surface,wl_callback,wl_subsurface,wl_pointer};
 
-use {CreationError, MouseCursor, CursorState, Event, WindowAttributes};
+use {CreationError, MouseCursor, CursorState, CustomCursor, Icon, Event, WindowAttributes, Selection, ClipboardData};
+use {ParentHandle, PixelFormat, Rect};
 use platform::MonitorId as PlatformMonitorId;
 
 use super::WaylandContext;
 use super::wayland_window;
 use super::wayland_window::DecoratedSurface;
+use super::frame::{Frame, FrameAction};
+use super::software::{ShmBuffer, SoftwareHandler};
+use super::xdg_shell::{xdg_surface, xdg_toplevel};
+use super::xdg_decoration::zxdg_toplevel_decoration_v1;
+use super::pointer_constraints::{zwp_locked_pointer_v1, zwp_confined_pointer_v1};
 
 #[derive(Clone)]
 pub struct WindowProxy {
@@ -32,8 +39,40 @@ pub struct Window {
     eviter: Arc<Mutex<VecDeque<Event>>>,
     surface: Arc<wl_surface::WlSurface>,
     size: Mutex<(u32, u32)>,
+    decorations: Mutex<bool>,
+    fullscreen: Mutex<bool>,
+    transparent: bool,
+    shell: Shell,
+    position: Mutex<(i32, i32)>,
+    cursor_state: Mutex<CursorState>,
+    pointer_constraint: Mutex<Option<PointerConstraint>>,
     handler_id: usize,
-    decorated_id: usize
+    software_id: usize
+}
+
+// How the surface of the window is shown
+enum Shell {
+    // through `xdg_shell`, with the id of the `XdgHandler`
+    Xdg(usize),
+    // through `wl_shell`, with the id of the `DecoratedSurface` that also draws the borders
+    Wl(usize),
+    // as a subsurface of a surface of the host, without shell surface
+    Embedded(wl_subsurface::WlSubsurface)
+}
+
+// What keeps the pointer from leaving the window
+enum PointerConstraint {
+    Locked(zwp_locked_pointer_v1::ZwpLockedPointerV1),
+    Confined(zwp_confined_pointer_v1::ZwpConfinedPointerV1)
+}
+
+impl PointerConstraint {
+    fn destroy(&self) {
+        match *self {
+            PointerConstraint::Locked(ref locked) => { locked.destroy(); },
+            PointerConstraint::Confined(ref confined) => { confined.destroy(); }
+        }
+    }
 }
 
 pub struct PollEventsIterator<'a> {
@@ -63,18 +102,115 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 impl Window {
     pub fn new(ctxt: Arc<WaylandContext>, attributes: &WindowAttributes)  -> Result<Window, CreationError>
     {
+        let parent = match attributes.parent {
+            Some(ParentHandle::Wayland { surface, .. }) if !surface.is_null() => Some(surface),
+            Some(other) => return Err(CreationError::OsError(format!("Expected a Wayland surface as parent, got {:?}",
+                                                                     other))),
+            None => None
+        };
+
         let (width, height) = attributes.dimensions.unwrap_or((800,600));
+        let (width, height) = {
+            use std::cmp::{min, max};
+            let (max_width, max_height) = attributes.max_dimensions.unwrap_or((width, height));
+            let (min_width, min_height) = attributes.min_dimensions.unwrap_or((0, 0));
+            (max(min(width, max_width), min_width), max(min(height, max_height), min_height))
+        };
 
         let mut evq = ctxt.display.create_event_queue();
 
-        let (surface, eviter, decorated) = ctxt.create_window::<DecoratedHandler>();
+        // an embedded window is positioned and sized by its host, without shell surface
+        if let Some(parent) = parent {
+            let (surface, eviter, subsurface) = ctxt.create_subsurface(parent);
+            let handler_id = evq.add_handler_with_init(WindowHandler::new(surface.clone(), eviter.clone()));
+            let software_id = evq.add_handler(SoftwareHandler::new(eviter.clone()));
+            if attributes.multitouch {
+                ctxt.enable_touch(&eviter);
+            }
+            ctxt.flush();
+
+            return Ok(Window {
+                ctxt: ctxt,
+                evq: Mutex::new(evq),
+                eviter: eviter,
+                surface: surface,
+                size: Mutex::new((width, height)),
+                decorations: Mutex::new(false),
+                fullscreen: Mutex::new(false),
+                transparent: attributes.transparent,
+                shell: Shell::Embedded(subsurface),
+                position: Mutex::new((0, 0)),
+                cursor_state: Mutex::new(CursorState::Normal),
+                pointer_constraint: Mutex::new(None),
+                handler_id: handler_id,
+                software_id: software_id
+            });
+        }
+
+        if let Some((surface, eviter, xdg_surface, toplevel, decoration)) =
+            ctxt.create_xdg_window()
+        {
+            toplevel.set_title(attributes.title.clone());
+            if !attributes.window_icon.is_empty() && !ctxt.set_toplevel_icon(&toplevel, &attributes.window_icon) {
+                warn!("[winit] The compositor doesn't support window icons.");
+            }
+            let frame = ctxt.create_frame(&surface, (width, height));
+            let mut xdg = XdgHandler::new(xdg_surface, toplevel, decoration, frame, eviter.clone(), (width, height));
+            xdg.set_decorations(attributes.decorations);
+            xdg.constraints.min_dimensions = attributes.min_dimensions;
+            xdg.constraints.max_dimensions = attributes.max_dimensions;
+            xdg.send_size_limits();
+            if let Some(PlatformMonitorId::Wayland(ref monitor_id)) = attributes.monitor {
+                ctxt.with_output(monitor_id.clone(), |output| { xdg.toplevel.set_fullscreen(Some(output)); });
+            }
+            let xdg_id = evq.add_handler_with_init(xdg);
+            let handler_id = evq.add_handler_with_init(WindowHandler::new(surface.clone(), eviter.clone()));
+            let software_id = evq.add_handler(SoftwareHandler::new(eviter.clone()));
+            if attributes.multitouch {
+                ctxt.enable_touch(&eviter);
+            }
+
+            // the surface can only be drawn to once the compositor configured it, which it does
+            // in response to a commit without buffer
+            surface.commit();
+            evq.sync_roundtrip().expect("Wayland connection unexpectedly lost");
+
+            return Ok(Window {
+                ctxt: ctxt,
+                evq: Mutex::new(evq),
+                eviter: eviter,
+                surface: surface,
+                size: Mutex::new((width, height)),
+                decorations: Mutex::new(attributes.decorations),
+                fullscreen: Mutex::new(attributes.monitor.is_some()),
+                transparent: attributes.transparent,
+                shell: Shell::Xdg(xdg_id),
+                position: Mutex::new((0, 0)),
+                cursor_state: Mutex::new(CursorState::Normal),
+                pointer_constraint: Mutex::new(None),
+                handler_id: handler_id,
+                software_id: software_id
+            });
+        }
+
+        // the compositor only supports wl_shell
+
+        let (surface, eviter, decorated) = match ctxt.create_window::<DecoratedHandler>() {
+            Some(window) => window,
+            None => return Err(CreationError::OsError("The compositor supports neither xdg_shell nor wl_shell"
+                                                      .to_string()))
+        };
 
         // init DecoratedSurface
         let decorated_id = evq.add_handler_with_init(decorated);
         {
             let mut state = evq.state();
             let decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(decorated_id);
-            *(decorated.handler()) = Some(DecoratedHandler::new());
+            let mut handler = DecoratedHandler::new();
+            handler.min_dimensions = attributes.min_dimensions;
+            handler.max_dimensions = attributes.max_dimensions;
+            *(decorated.handler()) = Some(handler);
+            decorated.set_title(attributes.title.clone());
 
             if let Some(PlatformMonitorId::Wayland(ref monitor_id)) = attributes.monitor {
                 ctxt.with_output(monitor_id.clone(), |output| {
@@ -92,8 +228,12 @@ impl Window {
         }
 
         // init general handler
-        let handler = WindowHandler::new();
+        let handler = WindowHandler::new(surface.clone(), eviter.clone());
         let handler_id = evq.add_handler_with_init(handler);
+        let software_id = evq.add_handler(SoftwareHandler::new(eviter.clone()));
+        if attributes.multitouch {
+            ctxt.enable_touch(&eviter);
+        }
 
         Ok(Window {
             ctxt: ctxt,
@@ -101,32 +241,82 @@ impl Window {
             eviter: eviter,
             surface: surface,
             size: Mutex::new((width, height)),
+            decorations: Mutex::new(attributes.decorations),
+            fullscreen: Mutex::new(attributes.monitor.is_some()),
+            transparent: attributes.transparent,
+            shell: Shell::Wl(decorated_id),
+            position: Mutex::new((0, 0)),
+            cursor_state: Mutex::new(CursorState::Normal),
+            pointer_constraint: Mutex::new(None),
             handler_id: handler_id,
-            decorated_id: decorated_id
+            software_id: software_id
         })
     }
 
+    // Runs `f` with the wl_shell surface of the window, if it has one.
+    fn with_decorated<F, T>(&self, f: F) -> Option<T>
+        where F: FnOnce(&mut DecoratedSurface<DecoratedHandler>) -> T
+    {
+        let decorated_id = match self.shell {
+            Shell::Wl(id) => id,
+            _ => return None
+        };
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        Some(f(state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(decorated_id)))
+    }
+
+    // Runs `f` with the xdg_shell toplevel of the window, if it has one.
+    fn with_xdg<F, T>(&self, f: F) -> Option<T>
+        where F: FnOnce(&mut XdgHandler) -> T
+    {
+        let xdg_id = match self.shell {
+            Shell::Xdg(id) => id,
+            _ => return None
+        };
+        let mut guard = self.evq.lock().unwrap();
+        let mut state = guard.state();
+        Some(f(state.get_mut_handler::<XdgHandler>(xdg_id)))
+    }
+
     fn process_resize(&self) {
-        use std::cmp::max;
-        let mut evq_guard = self.evq.lock().unwrap();
-        let mut state = evq_guard.state();
-        let newsize = {
-            let decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(self.decorated_id);
+        let newsize = self.with_decorated(|decorated| {
             let newsize = decorated.handler().as_mut().and_then(|h| h.take_newsize());
             if let Some((w, h)) = newsize {
                 decorated.resize(w as i32, h as i32);
-                *self.size.lock().unwrap() = (w, h);
             }
             newsize
+        }).or_else(|| self.with_xdg(|xdg| xdg.take_newsize()));
+        if let Some(Some(size)) = newsize {
+            self.resized(size);
+        }
+    }
+
+    // Reports the focus of an xdg_shell window, when the seat has no keyboard to report it.
+    fn process_activation(&self) {
+        let activated = match self.with_xdg(|xdg| xdg.take_activation()) {
+            Some(Some(activated)) => activated,
+            _ => return
         };
+        if !self.ctxt.has_keyboard() {
+            self.eviter.lock().unwrap().push_back(Event::Focused(activated));
+        }
+    }
+
+    // Reports the new size of the window.
+    fn resized(&self, (w, h): (u32, u32)) {
+        *self.size.lock().unwrap() = (w, h);
+        let mut evq_guard = self.evq.lock().unwrap();
+        let mut state = evq_guard.state();
         // callback_resize if any
-        if let Some((w, h)) = newsize {
-            let mut handler = state.get_mut_handler::<WindowHandler>(self.handler_id);
-            if let Some(ref callback) = handler.resize_callback {
-                callback(w, h);
-            }
-            self.eviter.lock().unwrap().push_back(Event::Resized(w,h));
+        let mut handler = state.get_mut_handler::<WindowHandler>(self.handler_id);
+        if let Some(ref callback) = handler.resize_callback {
+            callback(w, h);
         }
+        let mut eviter = self.eviter.lock().unwrap();
+        eviter.push_back(Event::Resized(w,h));
+        // the whole content must be drawn again at the new size
+        eviter.push_back(Event::Refresh(vec![Rect { x: 0, y: 0, width: w, height: h }]));
     }
 
     fn next_event(&self, block: bool) -> Option<Event> {
@@ -152,6 +342,7 @@ impl Window {
             self.evq.lock().unwrap().dispatch_pending();
             // some events were dispatched, need to process a potential resising
             self.process_resize();
+            self.process_activation();
         }
 
         let mut evt = {
@@ -167,6 +358,7 @@ impl Window {
                 self.evq.lock().unwrap().dispatch_pending();
                 // some events were dispatched, need to process a potential resising
                 self.process_resize();
+                self.process_activation();
             }
             // try again
             let mut guard = self.eviter.lock().unwrap();
@@ -176,10 +368,97 @@ impl Window {
     }
 
     pub fn set_title(&self, title: &str) {
-        let mut guard = self.evq.lock().unwrap();
-        let mut state = guard.state();
-        let mut decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(self.decorated_id);
-        decorated.set_title(title.into())
+        self.with_decorated(|decorated| decorated.set_title(title.into()));
+        self.with_xdg(|xdg| { xdg.toplevel.set_title(title.into()); });
+    }
+
+    pub fn set_fullscreen(&self, monitor: Option<PlatformMonitorId>) {
+        match self.shell {
+            Shell::Embedded(_) => {
+                warn!("[winit] An embedded window can't be made fullscreen.");
+                return;
+            },
+            Shell::Xdg(_) => {
+                // the new state is reported by the compositor
+                self.with_xdg(|xdg| match monitor {
+                    Some(PlatformMonitorId::Wayland(ref monitor_id)) => {
+                        self.ctxt.with_output(monitor_id.clone(), |output| {
+                            xdg.toplevel.set_fullscreen(Some(output));
+                        });
+                    },
+                    Some(_) => { xdg.toplevel.set_fullscreen(None); },
+                    None => { xdg.toplevel.unset_fullscreen(); }
+                });
+                return;
+            },
+            Shell::Wl(_) => ()
+        }
+
+        let mut fullscreen = self.fullscreen.lock().unwrap();
+        self.with_decorated(|decorated| {
+            match monitor {
+                Some(PlatformMonitorId::Wayland(ref monitor_id)) => {
+                    self.ctxt.with_output(monitor_id.clone(), |output| {
+                        decorated.set_fullscreen(
+                            wl_shell_surface::FullscreenMethod::Default,
+                            0,
+                            Some(output)
+                        )
+                    });
+                },
+                // let the compositor choose the output
+                Some(_) => decorated.set_fullscreen(wl_shell_surface::FullscreenMethod::Default, 0, None),
+                // going back to toplevel also leaves fullscreen
+                None => decorated.set_decorate(*self.decorations.lock().unwrap()),
+            }
+        });
+
+        // wl_shell doesn't report state changes, so we assume the compositor complied
+        let is_fullscreen = monitor.is_some();
+        if *fullscreen != is_fullscreen {
+            *fullscreen = is_fullscreen;
+            self.eviter.lock().unwrap().push_back(Event::Fullscreen(is_fullscreen));
+        }
+    }
+
+    pub fn set_decorations(&self, decorations: bool) {
+        *self.decorations.lock().unwrap() = decorations;
+        self.with_xdg(|xdg| xdg.set_decorations(decorations));
+        // a fullscreen window has no decorations, they are restored when leaving fullscreen
+        if !*self.fullscreen.lock().unwrap() {
+            self.with_decorated(|decorated| decorated.set_decorate(decorations));
+        }
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        let done = self.with_xdg(|xdg| {
+            if maximized { xdg.toplevel.set_maximized(); } else { xdg.toplevel.unset_maximized(); }
+        });
+        if done.is_none() {
+            // TODO: wl_shell supports maximizing, but wayland-window doesn't expose it
+            warn!("[winit] Maximizing a window is only supported with xdg_shell on wayland.");
+        }
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        // wl_shell has no notion of minimized windows, and xdg_shell only lets the user
+        // restore them
+        let done = self.with_xdg(|xdg| if minimized { xdg.toplevel.set_minimized(); });
+        if done.is_none() || !minimized {
+            warn!("[winit] Only xdg_shell windows can be minimized on wayland, and not restored.");
+        }
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.with_xdg(|xdg| xdg.maximized).unwrap_or(false)
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        false
     }
 
     #[inline]
@@ -194,13 +473,22 @@ impl Window {
 
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
-        // Not possible with wayland
-        None
+        // only known for embedded windows, relative to their parent
+        match self.shell {
+            Shell::Embedded(_) => Some(*self.position.lock().unwrap()),
+            _ => None
+        }
     }
 
     #[inline]
-    pub fn set_position(&self, _x: i32, _y: i32) {
-        // Not possible with wayland
+    pub fn set_position(&self, x: i32, y: i32) {
+        // only possible for embedded windows, the position is applied once the host commits
+        // its surface
+        if let Shell::Embedded(ref subsurface) = self.shell {
+            subsurface.set_position(x, y);
+            *self.position.lock().unwrap() = (x, y);
+            self.ctxt.flush();
+        }
     }
 
     pub fn get_inner_size(&self) -> Option<(u32, u32)> {
@@ -209,6 +497,19 @@ impl Window {
 
     #[inline]
     pub fn get_outer_size(&self) -> Option<(u32, u32)> {
+        // the borders drawn by the compositor around xdg_shell windows are unknown, only the
+        // ones we draw are counted
+        if let Some(size) = self.with_xdg(|xdg| xdg.frame.add_borders(xdg.size)) {
+            return Some(size);
+        }
+        // wl_shell windows have no borders while fullscreen
+        let bordered = match self.shell {
+            Shell::Wl(_) => *self.decorations.lock().unwrap() && !*self.fullscreen.lock().unwrap(),
+            _ => false
+        };
+        if !bordered {
+            return self.get_inner_size();
+        }
         let (w, h) = self.size.lock().unwrap().clone();
         let (w, h) = super::wayland_window::add_borders(w as i32, h as i32);
         Some((w as u32, h as u32))
@@ -217,10 +518,146 @@ impl Window {
     #[inline]
     // NOTE: This will only resize the borders, the contents must be updated by the user
     pub fn set_inner_size(&self, x: u32, y: u32) {
+        match self.shell {
+            // the size of a subsurface is the one of its buffers
+            Shell::Embedded(_) => self.resized((x, y)),
+            Shell::Xdg(_) => { self.with_xdg(|xdg| xdg.constraints.newsize = Some((x, y))); },
+            Shell::Wl(_) => { self.with_decorated(|decorated| decorated.resize(x as i32, y as i32)); }
+        }
+    }
+
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.update_size_constraints(|handler| handler.min_dimensions = dimensions);
+    }
+
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.update_size_constraints(|handler| handler.max_dimensions = dimensions);
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        let size = *self.size.lock().unwrap();
+        self.update_size_constraints(|handler| {
+            handler.fixed_dimensions = if resizable { None } else { Some(size) };
+        });
+    }
+
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        self.update_size_constraints(|handler| handler.aspect_ratio = ratio);
+    }
+
+    pub fn set_resize_increments(&self, increments: Option<(u32, u32)>) {
+        self.update_size_constraints(|handler| handler.resize_increments = increments);
+    }
+
+    // Modifies the constraints and resizes the window if its current size doesn't satisfy
+    // them anymore. The resize is applied when the events are next processed.
+    fn update_size_constraints<F>(&self, f: F) where F: FnOnce(&mut DecoratedHandler) {
+        let (width, height) = *self.size.lock().unwrap();
+        let update = |handler: &mut DecoratedHandler| {
+            f(handler);
+            let newsize = handler.constrain(width, height);
+            if newsize != (width, height) {
+                handler.newsize = Some(newsize);
+            }
+        };
+        match self.shell {
+            Shell::Xdg(_) => {
+                self.with_xdg(|xdg| {
+                    update(&mut xdg.constraints);
+                    xdg.send_size_limits();
+                });
+            },
+            Shell::Wl(_) => {
+                self.with_decorated(|decorated| {
+                    if let Some(ref mut handler) = *decorated.handler() {
+                        update(handler);
+                    }
+                });
+            },
+            Shell::Embedded(_) => ()
+        }
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, selection: Selection, contents: Vec<ClipboardData>) {
+        self.ctxt.set_selection(selection, contents)
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, selection: Selection, mime_type: &str) -> Option<Vec<u8>> {
+        self.ctxt.get_selection(selection, mime_type)
+    }
+
+    pub fn request_redraw(&self) {
         let mut guard = self.evq.lock().unwrap();
+        let frame_pending = {
+            let mut state = guard.state();
+            let handler = state.get_mut_handler::<WindowHandler>(self.handler_id);
+            if handler.frame_pending {
+                handler.redraw_requested = true;
+            }
+            handler.frame_pending
+        };
+        if frame_pending {
+            return;
+        }
+
+        // the callback is done once the compositor shows the frame drawn in response to this
+        // event, which it doesn't do while the window is hidden
+        let callback = self.surface.frame().expect("Surface cannot be dead");
+        guard.register::<_, WindowHandler>(&callback, self.handler_id);
+        // the callback only takes effect once committed, which drawing the frame may not do
+        self.surface.commit();
+        self.ctxt.flush();
         let mut state = guard.state();
-        let mut decorated = state.get_mut_handler::<DecoratedSurface<DecoratedHandler>>(self.decorated_id);
-        decorated.resize(x as i32, y as i32);
+        state.get_mut_handler::<WindowHandler>(self.handler_id).frame_pending = true;
+        self.eviter.lock().unwrap().push_back(Event::RedrawRequested);
+    }
+
+    pub fn present_pixels(&self, pixels: &[u8], width: u32, height: u32, format: PixelFormat,
+                          damage: Option<&[Rect]>)
+    {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let mut guard = self.evq.lock().unwrap();
+        let free_buffer = {
+            let mut state = guard.state();
+            state.get_mut_handler::<SoftwareHandler>(self.software_id).find_free_buffer(width, height)
+        };
+        let index = match free_buffer {
+            Some(index) => index,
+            None => {
+                let can_add = {
+                    let mut state = guard.state();
+                    state.get_mut_handler::<SoftwareHandler>(self.software_id).can_add_buffer()
+                };
+                if !can_add {
+                    return;
+                }
+                // the compositor may still hold the previous buffers, in which case one more is needed
+                let buffer = ShmBuffer::new(&self.ctxt, width, height, self.transparent);
+                guard.register::<_, SoftwareHandler>(buffer.get_buffer(), self.software_id);
+                let mut state = guard.state();
+                state.get_mut_handler::<SoftwareHandler>(self.software_id).add_buffer(buffer)
+            }
+        };
+
+        let mut state = guard.state();
+        state.get_mut_handler::<SoftwareHandler>(self.software_id)
+             .present(index, &self.surface, pixels, format, damage);
+        self.ctxt.flush();
+    }
+
+    #[inline]
+    pub fn start_drag(&self, contents: Vec<ClipboardData>) {
+        self.ctxt.start_drag(&self.surface, self.eviter.clone(), contents);
+    }
+
+    #[inline]
+    pub fn get_clipboard_mime_types(&self, selection: Selection) -> Vec<String> {
+        self.ctxt.get_selection_mime_types(selection)
     }
 
     #[inline]
@@ -254,19 +691,60 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor(&self, _cursor: MouseCursor) {
-        // TODO
+    pub fn set_cursor(&self, cursor: MouseCursor) {
+        self.ctxt.set_cursor(&self.eviter, cursor);
+        self.ctxt.flush();
+    }
+
+    pub fn set_window_icon(&self, icon: &[Icon]) -> Result<(), CreationError> {
+        // only xdg_shell windows can have an icon
+        let set = self.with_xdg(|xdg| self.ctxt.set_toplevel_icon(&xdg.toplevel, icon)).unwrap_or(false);
+        if !set {
+            return Err(CreationError::NotSupported);
+        }
+        self.surface.commit();
+        self.ctxt.flush();
+        Ok(())
     }
 
     #[inline]
+    pub fn request_user_attention(&self) {
+        // activating a surface needs a token of xdg_activation_v1, which isn't supported yet
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        self.ctxt.set_custom_cursor(&self.eviter, cursor);
+        self.ctxt.flush();
+    }
+
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        use CursorState::{Grab, Normal, Hide};
-        // TODO : not yet possible on wayland to grab cursor
-        match state {
-            Grab => Err("Cursor cannot be grabbed on wayland yet.".to_string()),
-            Hide => Err("Cursor cannot be hidden on wayland yet.".to_string()),
-            Normal => Ok(())
+        use CursorState::{Grab, Normal, Hide, Lock};
+
+        let mut cursor_state = self.cursor_state.lock().unwrap();
+        if *cursor_state == state {
+            return Ok(());
+        }
+
+        let mut pointer_constraint = self.pointer_constraint.lock().unwrap();
+        let constraint = match state {
+            Normal | Hide => None,
+            Grab => match self.ctxt.confine_pointer(&self.surface) {
+                Some(confined) => Some(PointerConstraint::Confined(confined)),
+                None => return Err("The compositor doesn't support pointer constraints.".to_string())
+            },
+            Lock => match self.ctxt.lock_pointer(&self.surface) {
+                Some(locked) => Some(PointerConstraint::Locked(locked)),
+                None => return Err("The compositor doesn't support pointer constraints.".to_string())
+            }
+        };
+        if let Some(old) = mem::replace(&mut *pointer_constraint, constraint) {
+            old.destroy();
         }
+        self.ctxt.set_cursor_hidden(&self.eviter, state == Hide || state == Lock);
+        *cursor_state = state;
+        self.ctxt.flush();
+        Ok(())
     }
 
     #[inline]
@@ -276,9 +754,17 @@ impl Window {
     }
 
     #[inline]
-    pub fn set_cursor_position(&self, _x: i32, _y: i32) -> Result<(), ()> {
-        // TODO: not yet possible on wayland
-        Err(())
+    pub fn set_cursor_position(&self, x: i32, y: i32) -> Result<(), ()> {
+        // the compositor only moves the cursor to the hint once the lock is released
+        match *self.pointer_constraint.lock().unwrap() {
+            Some(PointerConstraint::Locked(ref locked)) => {
+                locked.set_cursor_position_hint(x as f64, y as f64);
+                self.surface.commit();
+                self.ctxt.flush();
+                Ok(())
+            },
+            _ => Err(())
+        }
     }
     
     pub fn get_display(&self) -> &wl_display::WlDisplay {
@@ -292,20 +778,100 @@ impl Window {
 
 impl Drop for Window {
     fn drop(&mut self) {
+        if let Some(ref constraint) = *self.pointer_constraint.lock().unwrap() {
+            constraint.destroy();
+        }
+        match self.shell {
+            Shell::Embedded(ref subsurface) => { subsurface.destroy(); },
+            Shell::Xdg(_) => {
+                self.with_xdg(|xdg| {
+                    if let Some(ref decoration) = xdg.decoration {
+                        decoration.destroy();
+                    }
+                    xdg.toplevel.destroy();
+                    xdg.xdg_surface.destroy();
+                });
+            },
+            Shell::Wl(_) => ()
+        }
         self.surface.destroy();
         self.ctxt.prune_dead_windows();
     }
 }
 
 struct DecoratedHandler {
-    newsize: Option<(u32, u32)>
+    newsize: Option<(u32, u32)>,
+    // the compositor doesn't know about these constraints, so they are applied
+    // to the sizes it suggests
+    min_dimensions: Option<(u32, u32)>,
+    max_dimensions: Option<(u32, u32)>,
+    fixed_dimensions: Option<(u32, u32)>,
+    aspect_ratio: Option<(u32, u32)>,
+    resize_increments: Option<(u32, u32)>,
 }
 
 impl DecoratedHandler {
-    fn new() -> DecoratedHandler { DecoratedHandler { newsize: None }}
+    fn new() -> DecoratedHandler {
+        DecoratedHandler {
+            newsize: None,
+            min_dimensions: None,
+            max_dimensions: None,
+            fixed_dimensions: None,
+            aspect_ratio: None,
+            resize_increments: None,
+        }
+    }
+
     fn take_newsize(&mut self) -> Option<(u32, u32)> {
         self.newsize.take()
     }
+
+    /// Returns the size closest to `(width, height)` that satisfies the constraints.
+    fn constrain(&self, width: u32, height: u32) -> (u32, u32) {
+        use std::cmp::{min, max};
+
+        if let Some(dimensions) = self.fixed_dimensions {
+            return dimensions;
+        }
+
+        let (mut width, mut height) = (width, height);
+
+        // the ratio and the increments only shrink the size, so they are applied within the
+        // limits, which they can't push it out of
+        if let Some((max_width, max_height)) = self.max_dimensions {
+            width = min(width, max_width);
+            height = min(height, max_height);
+        }
+
+        let (min_width, min_height) = self.min_dimensions.unwrap_or((0, 0));
+        width = max(width, min_width);
+        height = max(height, min_height);
+
+        // shrink one of the dimensions so that the size fits in the suggested one
+        if let Some((numerator, denominator)) = self.aspect_ratio {
+            if numerator != 0 && denominator != 0 {
+                if width as u64 * denominator as u64 > height as u64 * numerator as u64 {
+                    width = (height as u64 * numerator as u64 / denominator as u64) as u32;
+                    width = max(width, min_width);
+                } else {
+                    height = (width as u64 * denominator as u64 / numerator as u64) as u32;
+                    height = max(height, min_height);
+                }
+            }
+        }
+
+        // increments are counted from the minimum size, like on X11
+        if let Some((width_inc, height_inc)) = self.resize_increments {
+            if width_inc != 0 {
+                width -= (width - min_width) % width_inc;
+            }
+            if height_inc != 0 {
+                height -= (height - min_height) % height_inc;
+            }
+        }
+
+        (max(width, 1), max(height, 1))
+    }
 }
 
 impl wayland_window::Handler for DecoratedHandler {
@@ -315,26 +881,389 @@ impl wayland_window::Handler for DecoratedHandler {
                  width: i32, height: i32)
     {
         use std::cmp::max;
-        self.newsize = Some((max(width,1) as u32, max(height,1) as u32));
+        self.newsize = Some(self.constrain(max(width,1) as u32, max(height,1) as u32));
+    }
+}
+
+// A window shown through `xdg_shell`. The compositor suggests sizes and states with a sequence of
+// events, which is applied once `xdg_surface.configure` ends it.
+struct XdgHandler {
+    xdg_surface: xdg_surface::XdgSurface,
+    toplevel: xdg_toplevel::XdgToplevel,
+    // set if the compositor can draw the decorations
+    decoration: Option<zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1>,
+    decorated: bool,
+    // the compositor draws the decorations, otherwise they are drawn by the frame
+    server_side: bool,
+    frame: Frame,
+    eviter: Arc<Mutex<VecDeque<Event>>>,
+    // the suggested sizes go through the same constraints as with wl_shell
+    constraints: DecoratedHandler,
+    size: (u32, u32),
+    // size before the window was maximized or made fullscreen, restored afterwards
+    floating_size: (u32, u32),
+    pending_size: (i32, i32),
+    pending_states: Vec<xdg_toplevel::State>,
+    maximized: bool,
+    fullscreen: bool,
+    activated: bool,
+    // the activation changed since it was last reported
+    activation: Option<bool>,
+}
+
+impl XdgHandler {
+    fn new(xdg_surface: xdg_surface::XdgSurface, toplevel: xdg_toplevel::XdgToplevel,
+           decoration: Option<zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1>, frame: Frame,
+           eviter: Arc<Mutex<VecDeque<Event>>>, size: (u32, u32)) -> XdgHandler
+    {
+        XdgHandler {
+            xdg_surface: xdg_surface,
+            toplevel: toplevel,
+            decoration: decoration,
+            decorated: false,
+            server_side: false,
+            frame: frame,
+            eviter: eviter,
+            constraints: DecoratedHandler::new(),
+            size: size,
+            floating_size: size,
+            pending_size: (0, 0),
+            pending_states: Vec::new(),
+            maximized: false,
+            fullscreen: false,
+            activated: false,
+            activation: None,
+        }
+    }
+
+    // Asks the compositor to draw the decorations or not. The mode it picks is reported by
+    // `zxdg_toplevel_decoration_v1.configure`, until then the frame stays as it is.
+    fn set_decorations(&mut self, decorations: bool) {
+        use self::zxdg_toplevel_decoration_v1::Mode;
+        self.decorated = decorations;
+        if let Some(ref decoration) = self.decoration {
+            let mode = if decorations { Mode::ServerSide } else { Mode::ClientSide };
+            decoration.set_mode(mode.to_raw());
+            if !self.frame.is_visible() {
+                self.server_side = decorations;
+            }
+        }
+        self.update_frame();
+    }
+
+    // Shows the frame if the window is decorated by us, and tells the compositor which part of
+    // the surfaces is the window.
+    fn update_frame(&mut self) {
+        let visible = self.decorated && !self.server_side && !self.fullscreen;
+        if visible == self.frame.is_visible() {
+            return;
+        }
+        self.frame.set_visible(visible);
+        self.update_geometry();
+        self.send_size_limits();
+    }
+
+    // The geometry is applied with the next commit of the surface.
+    fn update_geometry(&self) {
+        let (x, y, width, height) = self.frame.geometry();
+        self.xdg_surface.set_window_geometry(x, y, width, height);
+    }
+
+    fn take_newsize(&mut self) -> Option<(u32, u32)> {
+        let newsize = self.constraints.take_newsize();
+        if let Some(size) = newsize {
+            self.size = size;
+            self.frame.resize(size);
+            self.update_geometry();
+        }
+        newsize
+    }
+
+    fn take_activation(&mut self) -> Option<bool> {
+        self.activation.take()
+    }
+
+    // Tells the compositor the minimum and maximum sizes, where 0 means unlimited. They are
+    // applied with the next commit.
+    fn send_size_limits(&self) {
+        let constraints = &self.constraints;
+        let (min, max) = match constraints.fixed_dimensions {
+            Some(dimensions) => (Some(dimensions), Some(dimensions)),
+            None => (constraints.min_dimensions, constraints.max_dimensions)
+        };
+        // the limits include the frame, 0 still meaning unlimited
+        let (min_width, min_height) = min.map(|size| self.frame.add_borders(size)).unwrap_or((0, 0));
+        let (max_width, max_height) = max.map(|size| self.frame.add_borders(size)).unwrap_or((0, 0));
+        self.toplevel.set_min_size(min_width as i32, min_height as i32);
+        self.toplevel.set_max_size(max_width as i32, max_height as i32);
+    }
+}
+
+impl Init for XdgHandler {
+    fn init(&mut self, evqh: &mut EventQueueHandle, index: usize) {
+        evqh.register::<_, XdgHandler>(&self.xdg_surface, index);
+        evqh.register::<_, XdgHandler>(&self.toplevel, index);
+        if let Some(ref decoration) = self.decoration {
+            evqh.register::<_, XdgHandler>(decoration, index);
+        }
+        if let Some(pointer) = self.frame.pointer() {
+            evqh.register::<_, XdgHandler>(pointer, index);
+        }
+    }
+}
+
+impl zxdg_toplevel_decoration_v1::Handler for XdgHandler {
+    fn configure(&mut self,
+                 _: &mut EventQueueHandle,
+                 _: &zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1,
+                 mode: u32)
+    {
+        // the frame is drawn instead if the compositor refuses to draw the decorations, the
+        // size of the window changing along with the rest of the configuration
+        self.server_side = mode == zxdg_toplevel_decoration_v1::Mode::ServerSide.to_raw();
+        self.update_frame();
+    }
+}
+
+impl wl_pointer::Handler for XdgHandler {
+    // the pointer of the frame only handles its borders, the context handles the window
+
+    fn enter(&mut self, _: &mut EventQueueHandle, _: &wl_pointer::WlPointer, serial: u32,
+             surface: &wl_surface::WlSurface, x: f64, y: f64)
+    {
+        self.frame.pointer_entered(surface, serial, x, y);
+    }
+
+    fn leave(&mut self, _: &mut EventQueueHandle, _: &wl_pointer::WlPointer, _: u32, _: &wl_surface::WlSurface) {
+        self.frame.pointer_left();
+    }
+
+    fn motion(&mut self, _: &mut EventQueueHandle, _: &wl_pointer::WlPointer, _: u32, x: f64, y: f64) {
+        self.frame.pointer_moved(x, y);
+    }
+
+    fn button(&mut self, _: &mut EventQueueHandle, _: &wl_pointer::WlPointer, serial: u32, _: u32,
+              button: u32, state: wl_pointer::ButtonState)
+    {
+        // only the left button moves and resizes the window
+        if button != 0x110 || !self.frame.is_visible() {
+            return;
+        }
+        if let wl_pointer::ButtonState::Released = state {
+            return;
+        }
+        let seat = match self.frame.seat() {
+            Some(seat) => seat,
+            None => return
+        };
+        match self.frame.action() {
+            Some(FrameAction::Move) => { self.toplevel._move(seat, serial); },
+            Some(FrameAction::Resize(edge)) => { self.toplevel.resize(seat, serial, edge.to_raw()); },
+            None => ()
+        }
+    }
+}
+
+impl xdg_toplevel::Handler for XdgHandler {
+    fn configure(&mut self,
+                 _: &mut EventQueueHandle,
+                 _: &xdg_toplevel::XdgToplevel,
+                 width: i32, height: i32,
+                 states: Vec<u8>)
+    {
+        self.pending_size = (width, height);
+        // the states are an array of native-endian 32 bits values
+        self.pending_states = states.chunks(4).filter(|raw| raw.len() == 4).filter_map(|raw| {
+            let mut value = [0u8; 4];
+            value.copy_from_slice(raw);
+            xdg_toplevel::State::from_raw(u32::from_ne_bytes(value))
+        }).collect();
+    }
+
+    fn close(&mut self, _: &mut EventQueueHandle, _: &xdg_toplevel::XdgToplevel) {
+        self.eviter.lock().unwrap().push_back(Event::Closed);
+    }
+}
+
+impl xdg_surface::Handler for XdgHandler {
+    fn configure(&mut self, _: &mut EventQueueHandle, proxy: &xdg_surface::XdgSurface, serial: u32) {
+        let (mut maximized, mut fullscreen, mut resizing, mut activated) = (false, false, false, false);
+        for state in self.pending_states.drain(..) {
+            match state {
+                xdg_toplevel::State::Maximized => maximized = true,
+                xdg_toplevel::State::Fullscreen => fullscreen = true,
+                xdg_toplevel::State::Resizing => resizing = true,
+                xdg_toplevel::State::Activated => activated = true,
+                // the tiled edges don't change what we draw
+                _ => ()
+            }
+        }
+        if self.activated != activated {
+            self.activated = activated;
+            self.activation = Some(activated);
+        }
+
+        let was_floating = !self.maximized && !self.fullscreen;
+        let floating = !maximized && !fullscreen;
+        {
+            let mut eviter = self.eviter.lock().unwrap();
+            if self.maximized != maximized {
+                eviter.push_back(Event::Maximized(maximized));
+            }
+            if self.fullscreen != fullscreen {
+                eviter.push_back(Event::Fullscreen(fullscreen));
+            }
+        }
+        self.maximized = maximized;
+        self.fullscreen = fullscreen;
+        // a fullscreen window has no frame
+        self.update_frame();
+
+        // the suggested size includes the frame
+        let (width, height) = self.frame.subtract_borders(self.pending_size);
+        let newsize = if width > 0 && height > 0 {
+            if maximized {
+                // a maximized window must have exactly the suggested size
+                Some((width as u32, height as u32))
+            } else if resizing {
+                // the constraints are also sent to the compositor, which shouldn't suggest a
+                // smaller size than the minimum one
+                let (w, h) = self.constraints.constrain(width as u32, height as u32);
+                Some((::std::cmp::min(w, width as u32), ::std::cmp::min(h, height as u32)))
+            } else {
+                Some(self.constraints.constrain(width as u32, height as u32))
+            }
+        } else if floating && !was_floating {
+            // the compositor lets us pick the size
+            Some(self.floating_size)
+        } else {
+            None
+        };
+        if was_floating && !floating {
+            self.floating_size = self.size;
+        }
+        if let Some(newsize) = newsize {
+            if newsize != self.size {
+                self.constraints.newsize = Some(newsize);
+            }
+        }
+
+        proxy.ack_configure(serial);
     }
 }
 
+declare_handler!(XdgHandler, xdg_toplevel::Handler, xdg_toplevel::XdgToplevel);
+declare_handler!(XdgHandler, xdg_surface::Handler, xdg_surface::XdgSurface);
+declare_handler!(XdgHandler, zxdg_toplevel_decoration_v1::Handler, zxdg_toplevel_decoration_v1::ZxdgToplevelDecorationV1);
+declare_handler!(XdgHandler, wl_pointer::Handler, wl_pointer::WlPointer);
+
 struct WindowHandler {
     my_id: usize,
     resize_callback: Option<fn(u32,u32)>,
+    surface: Arc<wl_surface::WlSurface>,
+    eviter: Arc<Mutex<VecDeque<Event>>>,
+    // a frame callback was requested and isn't done yet
+    frame_pending: bool,
+    // `request_redraw` was called while waiting for the frame callback
+    redraw_requested: bool,
 }
 
 impl WindowHandler {
-    fn new() -> WindowHandler {
+    fn new(surface: Arc<wl_surface::WlSurface>, eviter: Arc<Mutex<VecDeque<Event>>>) -> WindowHandler {
         WindowHandler {
             my_id: 0,
-            resize_callback: None
+            resize_callback: None,
+            surface: surface,
+            eviter: eviter,
+            frame_pending: false,
+            redraw_requested: false
         }
     }
 }
 
+impl wl_callback::Handler for WindowHandler {
+    fn done(&mut self, evqh: &mut EventQueueHandle, _proxy: &wl_callback::WlCallback, _data: u32) {
+        self.frame_pending = false;
+        if self.redraw_requested {
+            // the frame drawn in response to the event is paced as well
+            let callback = self.surface.frame().expect("Surface cannot be dead");
+            evqh.register::<_, WindowHandler>(&callback, self.my_id);
+            self.surface.commit();
+            self.frame_pending = true;
+            self.redraw_requested = false;
+            self.eviter.lock().unwrap().push_back(Event::RedrawRequested);
+        }
+    }
+}
+
+declare_handler!(WindowHandler, wl_callback::Handler, wl_callback::WlCallback);
+
 impl Init for WindowHandler {
     fn init(&mut self, evqh: &mut EventQueueHandle, index: usize) {
         self.my_id = index;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::DecoratedHandler;
+
+    #[test]
+    fn constrain_without_constraints() {
+        let handler = DecoratedHandler::new();
+        assert_eq!(handler.constrain(640, 480), (640, 480));
+        // a window can't be empty
+        assert_eq!(handler.constrain(0, 0), (1, 1));
+    }
+
+    #[test]
+    fn constrain_min_max() {
+        let mut handler = DecoratedHandler::new();
+        handler.min_dimensions = Some((200, 100));
+        handler.max_dimensions = Some((800, 600));
+        assert_eq!(handler.constrain(100, 50), (200, 100));
+        assert_eq!(handler.constrain(1000, 1000), (800, 600));
+        assert_eq!(handler.constrain(300, 1000), (300, 600));
+    }
+
+    #[test]
+    fn constrain_fixed() {
+        let mut handler = DecoratedHandler::new();
+        handler.min_dimensions = Some((200, 100));
+        handler.fixed_dimensions = Some((400, 300));
+        assert_eq!(handler.constrain(1000, 50), (400, 300));
+    }
+
+    #[test]
+    fn constrain_aspect_ratio() {
+        let mut handler = DecoratedHandler::new();
+        handler.aspect_ratio = Some((16, 9));
+        // the dimension that is too large shrinks
+        assert_eq!(handler.constrain(1000, 1000), (1000, 562));
+        assert_eq!(handler.constrain(1600, 450), (800, 450));
+        // a ratio of zero is ignored
+        handler.aspect_ratio = Some((0, 9));
+        assert_eq!(handler.constrain(1000, 1000), (1000, 1000));
+    }
+
+    #[test]
+    fn constrain_resize_increments() {
+        let mut handler = DecoratedHandler::new();
+        handler.resize_increments = Some((10, 20));
+        assert_eq!(handler.constrain(157, 163), (150, 160));
+
+        // the increments are counted from the minimum size
+        handler.min_dimensions = Some((105, 110));
+        assert_eq!(handler.constrain(157, 163), (155, 150));
+        assert_eq!(handler.constrain(50, 50), (105, 110));
+    }
+
+    #[test]
+    fn constrain_max_then_increments() {
+        let mut handler = DecoratedHandler::new();
+        handler.resize_increments = Some((10, 10));
+        handler.max_dimensions = Some((95, 95));
+        // the size stays a multiple of the increments
+        assert_eq!(handler.constrain(200, 200), (90, 90));
+    }
+}
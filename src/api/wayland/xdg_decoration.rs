@@ -0,0 +1,24 @@
+//! The `xdg_decoration_unstable_v1` protocol, generated by the build script from
+//! `protocols/xdg-decoration-unstable-v1.xml`.
+
+pub use self::generated::client::*;
+
+mod generated {
+    // the generated code triggers a lot of warnings
+    #![allow(dead_code,non_camel_case_types,unused_unsafe,unused_variables)]
+    #![allow(non_upper_case_globals,non_snake_case,unused_imports)]
+    #![allow(missing_docs)]
+    #![allow(unknown_lints,static_mut_refs,unused_braces)]
+
+    pub mod interfaces {
+        #[doc(hidden)] pub use super::super::super::xdg_shell::interfaces::xdg_toplevel_interface;
+        include!(concat!(env!("OUT_DIR"), "/xdg_decoration_interfaces.rs"));
+    }
+
+    pub mod client {
+        #[doc(hidden)] pub use wayland_client::{Proxy, Handler, EventQueueHandle, RequestResult};
+        #[doc(hidden)] pub use super::interfaces;
+        #[doc(hidden)] pub use super::super::super::xdg_shell::xdg_toplevel;
+        include!(concat!(env!("OUT_DIR"), "/xdg_decoration_api.rs"));
+    }
+}
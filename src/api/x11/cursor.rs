@@ -0,0 +1,63 @@
+//! Custom cursors, loaded from images with Xcursor.
+
+use std::sync::Arc;
+
+use CustomCursor;
+use api::images::{premultiplied_argb, CursorCache};
+
+use super::ffi;
+use super::XConnection;
+
+/// The cursors made from the last custom images shown by a window.
+pub struct CustomCursors {
+    display: Arc<XConnection>,
+    cache: CursorCache<ffi::Cursor>,
+}
+
+impl CustomCursors {
+    pub fn new(display: &Arc<XConnection>) -> CustomCursors {
+        CustomCursors {
+            display: display.clone(),
+            cache: CursorCache::new(),
+        }
+    }
+
+    /// Returns the cursor showing `cursor`, or 0 if it couldn't be created.
+    pub fn get(&mut self, cursor: &CustomCursor) -> ffi::Cursor {
+        let display = &self.display;
+        // a cursor that was defined for the window lives as long as the window uses it
+        *self.cache.get(cursor, || load_cursor(display, cursor),
+                        |xcursor| free_cursor(display, xcursor))
+    }
+}
+
+impl Drop for CustomCursors {
+    fn drop(&mut self) {
+        let display = &self.display;
+        self.cache.clear(|xcursor| free_cursor(display, xcursor));
+    }
+}
+
+fn load_cursor(display: &XConnection, cursor: &CustomCursor) -> ffi::Cursor {
+    unsafe {
+        let image = (display.xcursor.XcursorImageCreate)(cursor.width as i32, cursor.height as i32);
+        if image.is_null() {
+            return 0;
+        }
+        (*image).xhot = cursor.hotspot.0;
+        (*image).yhot = cursor.hotspot.1;
+        (*image).delay = 0;
+        let pixels = premultiplied_argb(&cursor.rgba, cursor.width, cursor.height);
+        ::std::ptr::copy_nonoverlapping(pixels.as_ptr(), (*image).pixels, pixels.len());
+
+        let xcursor = (display.xcursor.XcursorImageLoadCursor)(display.display, image);
+        (display.xcursor.XcursorImageDestroy)(image);
+        xcursor
+    }
+}
+
+fn free_cursor(display: &XConnection, xcursor: ffi::Cursor) {
+    if xcursor != 0 {
+        unsafe { (display.xlib.XFreeCursor)(display.display, xcursor) };
+    }
+}
@@ -0,0 +1,606 @@
+//! Support for the XDND protocol, used to drag and drop data between X11 clients.
+//!
+//! See https://www.freedesktop.org/wiki/Specifications/XDND/ for the details.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use libc::{self, c_long};
+
+use {DragAction, Event};
+
+use super::ffi;
+use super::XConnection;
+use super::registry::Registry;
+use super::selection;
+use api::uri_list;
+
+// The version of the protocol that we implement
+const XDND_VERSION: c_long = 5;
+
+// How long the target of a drop has to read the data and answer with `XdndFinished`
+const FINISH_TIMEOUT_MS: u64 = 5000;
+
+lazy_static! {
+    // the messages for a window may be read by another one, which is also the case when one of
+    // our windows drags data over another one
+    static ref WINDOWS: Registry<Dnd> = Registry::new();
+}
+
+/// A drag coming from another client, currently over the window.
+struct IncomingDrag {
+    source: ffi::Window,
+    version: c_long,
+    // whether the source offers a list of files
+    has_uri_list: bool,
+    // files being dragged, fetched on the first position message
+    paths: Option<Vec<PathBuf>>,
+    // the selection was asked to be converted to the list of files
+    requested: bool,
+    position: Option<(i32, i32)>,
+    // the answer to this position is sent once the files are known
+    pending_position: Option<(i32, i32)>,
+    // the data was dropped before the files were known
+    dropped: bool,
+}
+
+/// A drag started by the window, while the pointer is grabbed.
+struct OutgoingDrag {
+    // atoms of the formats the data is offered in
+    types: Vec<ffi::Atom>,
+    // window under the pointer that understands XDND, with its version
+    target: Option<(ffi::Window, c_long)>,
+    accepted: bool,
+    action: ffi::Atom,
+    // a new position is only sent once the target answered the previous one
+    waiting_status: bool,
+    pending_position: Option<(libc::c_int, libc::c_int, ffi::Time)>,
+    // when the data was dropped, after which we wait for `XdndFinished`
+    dropped: Option<Instant>,
+}
+
+/// State of the drag and drop operations involving a window.
+pub struct Dnd {
+    incoming: Option<IncomingDrag>,
+    outgoing: Option<OutgoingDrag>,
+    // events for the window, which may come from messages read by another window
+    events: VecDeque<Event>,
+}
+
+/// Returns the state of `window` if it is one of our windows.
+pub fn find(display: &XConnection, window: ffi::Window) -> Option<Arc<Mutex<Dnd>>> {
+    WINDOWS.find(display, window)
+}
+
+impl Dnd {
+    /// Creates the state of `window`, which `find` returns as long as it is alive.
+    pub fn register(display: &XConnection, window: ffi::Window) -> Arc<Mutex<Dnd>> {
+        WINDOWS.register(display, window, Dnd {
+            incoming: None,
+            outgoing: None,
+            events: VecDeque::new(),
+        })
+    }
+
+    pub fn pop_event(&mut self) -> Option<Event> {
+        self.events.pop_front()
+    }
+
+    /// Returns true while a drag started by the window is in progress.
+    pub fn is_dragging(&self) -> bool {
+        self.outgoing.is_some()
+    }
+
+    /// Starts a drag of data offered in the formats `types`. The data itself must be served
+    /// through `XdndSelection`, which the window must own.
+    ///
+    /// The drag ends right away with `DragFinished` if the pointer can't be grabbed.
+    pub fn start_drag(&mut self, display: &XConnection, window: ffi::Window, types: Vec<ffi::Atom>) {
+        let grab = unsafe {
+            // targets read the full list of formats from this property
+            (display.xlib.XChangeProperty)(display.display, window, display.get_atom("XdndTypeList"),
+                                           ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                                           types.as_ptr() as *const u8, types.len() as libc::c_int);
+
+            // the pointer is grabbed so that we keep receiving its events outside of the window
+            let grab = (display.xlib.XGrabPointer)(display.display, window, ffi::False,
+                                                   (ffi::PointerMotionMask | ffi::ButtonReleaseMask) as libc::c_uint,
+                                                   ffi::GrabModeAsync, ffi::GrabModeAsync, 0, 0, ffi::CurrentTime);
+            (display.xlib.XFlush)(display.display);
+            grab
+        };
+        display.check_errors().expect("Failed to start dragging");
+
+        // without the grab, the release of the button outside of the window would never end
+        // the drag, for example while the host of the window holds the pointer
+        if grab != ffi::GrabSuccess {
+            self.events.push_back(Event::DragFinished { accepted: false, action: None });
+            return;
+        }
+
+        self.outgoing = Some(OutgoingDrag {
+            types: types,
+            target: None,
+            accepted: false,
+            action: 0,
+            waiting_status: false,
+            pending_position: None,
+            dropped: None,
+        });
+    }
+
+    /// Follows the pointer during a drag, notifying the windows it enters and leaves.
+    pub fn handle_motion(&mut self, display: &XConnection, window: ffi::Window,
+                         x_root: libc::c_int, y_root: libc::c_int, time: ffi::Time)
+    {
+        let drag = match self.outgoing {
+            Some(ref mut drag) if drag.dropped.is_none() => drag,
+            _ => return,
+        };
+
+        let target = find_drop_target(display, x_root, y_root);
+
+        if target.map(|t| t.0) != drag.target.map(|t| t.0) {
+            if let Some((old_target, _)) = drag.target {
+                send_client_message(display, old_target, display.get_atom("XdndLeave"),
+                                    [window as c_long, 0, 0, 0, 0]);
+            }
+
+            if let Some((new_target, version)) = target {
+                let types = &drag.types;
+                let atom = |i: usize| types.get(i).map(|&t| t as c_long).unwrap_or(0);
+                let version = ::std::cmp::min(version, XDND_VERSION);
+                send_client_message(display, new_target, display.get_atom("XdndEnter"), [
+                    window as c_long,
+                    // bit 0 tells the target to read `XdndTypeList`
+                    version << 24 | if types.len() > 3 { 1 } else { 0 },
+                    atom(0),
+                    atom(1),
+                    atom(2),
+                ]);
+            }
+
+            drag.target = target;
+            drag.accepted = false;
+            drag.action = 0;
+            drag.waiting_status = false;
+        }
+
+        if drag.target.is_some() {
+            drag.pending_position = Some((x_root, y_root, time));
+            if !drag.waiting_status {
+                send_position(display, window, drag);
+            }
+        }
+    }
+
+    /// Ends a drag when the pointer button is released, dropping the data if the target
+    /// accepted it.
+    pub fn handle_button_release(&mut self, display: &XConnection, window: ffi::Window, time: ffi::Time) {
+        let finished = match self.outgoing {
+            Some(ref mut drag) if drag.dropped.is_none() => {
+                unsafe { (display.xlib.XUngrabPointer)(display.display, time) };
+
+                match drag.target {
+                    Some((target, _)) if drag.accepted => {
+                        send_client_message(display, target, display.get_atom("XdndDrop"),
+                                            [window as c_long, 0, time as c_long, 0, 0]);
+                        drag.dropped = Some(Instant::now());
+                        // tells us if the target is destroyed before it answers
+                        !watch_destruction(display, target)
+                    },
+                    Some((target, _)) => {
+                        send_client_message(display, target, display.get_atom("XdndLeave"),
+                                            [window as c_long, 0, 0, 0, 0]);
+                        true
+                    },
+                    None => true,
+                }
+            },
+            _ => false,
+        };
+
+        if finished {
+            self.outgoing = None;
+            self.events.push_back(Event::DragFinished { accepted: false, action: None });
+        }
+    }
+
+    /// Handles a client message if it belongs to the XDND protocol, and queues the
+    /// corresponding events.
+    ///
+    /// Returns false if the message isn't an XDND one.
+    pub fn handle_client_message(&mut self, display: &XConnection, window: ffi::Window,
+                                 message: &ffi::XClientMessageEvent) -> bool
+    {
+        let data = message.data.as_longs();
+
+        if message.message_type == display.get_atom("XdndEnter") {
+            let source = data[0] as ffi::Window;
+            let version = data[1] >> 24;
+            let uri_list = display.get_atom("text/uri-list");
+
+            // the first three types are in the message, the complete list is in a property
+            let has_uri_list = if data[1] & 1 != 0 {
+                // the source may be destroyed at any time
+                let type_list = selection::try_read_property(display, source, display.get_atom("XdndTypeList"));
+                match type_list {
+                    Some((ty, atoms)) if ty == ffi::XA_ATOM => {
+                        atoms.chunks(4).filter(|atom| atom.len() == 4).any(|atom| {
+                            unsafe { ptr::read_unaligned(atom.as_ptr() as *const u32) as ffi::Atom == uri_list }
+                        })
+                    },
+                    _ => false,
+                }
+            } else {
+                data[2 .. 5].iter().any(|&atom| atom as ffi::Atom == uri_list)
+            };
+
+            self.incoming = Some(IncomingDrag {
+                source: source,
+                version: version,
+                has_uri_list: has_uri_list,
+                paths: None,
+                requested: false,
+                position: None,
+                pending_position: None,
+                dropped: false,
+            });
+
+        } else if message.message_type == display.get_atom("XdndPosition") {
+            let drag = match self.incoming {
+                Some(ref mut drag) if drag.source == data[0] as ffi::Window => drag,
+                _ => return true,
+            };
+
+            // the position is given in root window coordinates
+            let (root_x, root_y) = ((data[2] >> 16) as libc::c_int, (data[2] & 0xffff) as libc::c_int);
+            let position = translate_from_root(display, window, root_x, root_y);
+
+            if drag.has_uri_list && drag.paths.is_none() {
+                // the source doesn't send another position before it gets the status of this one
+                if !drag.requested {
+                    request_paths(display, window, data[3] as ffi::Time);
+                    drag.requested = true;
+                }
+                drag.pending_position = Some(position);
+                return true;
+            }
+
+            update_position(display, window, drag, position, &mut self.events);
+
+        } else if message.message_type == display.get_atom("XdndLeave") {
+            if let Some(drag) = self.incoming.take() {
+                if drag.position.is_some() && drag.paths.map(|p| !p.is_empty()).unwrap_or(false) {
+                    self.events.push_back(Event::HoveredFileCancelled);
+                }
+            }
+
+        } else if message.message_type == display.get_atom("XdndDrop") {
+            let waiting = match self.incoming {
+                Some(ref mut drag) if drag.has_uri_list && drag.paths.is_none() => {
+                    if !drag.requested {
+                        request_paths(display, window, data[2] as ffi::Time);
+                        drag.requested = true;
+                    }
+                    drag.dropped = true;
+                    true
+                },
+                Some(_) => false,
+                None => return true,
+            };
+
+            if !waiting {
+                let drag = self.incoming.take().unwrap();
+                finish_drop(display, window, drag, &mut self.events);
+            }
+
+        } else if message.message_type == display.get_atom("XdndStatus") {
+            if let Some(ref mut drag) = self.outgoing {
+                if drag.target.map(|t| t.0) == Some(data[0] as ffi::Window) {
+                    drag.accepted = data[1] & 1 != 0;
+                    drag.action = data[4] as ffi::Atom;
+                    drag.waiting_status = false;
+                    if drag.dropped.is_none() {
+                        send_position(display, window, drag);
+                    }
+                }
+            }
+
+        } else if message.message_type == display.get_atom("XdndFinished") {
+            let finished = match self.outgoing {
+                Some(ref drag) => drag.dropped.is_some() && drag.target.map(|t| t.0) == Some(data[0] as ffi::Window),
+                None => false,
+            };
+
+            if finished {
+                let drag = self.outgoing.take().unwrap();
+                // before version 5, the message only tells that the target is done
+                let version = drag.target.map(|t| t.1).unwrap_or(0);
+                let (accepted, action) = if version >= 5 {
+                    (data[1] & 1 != 0, data[2] as ffi::Atom)
+                } else {
+                    (true, drag.action)
+                };
+                self.events.push_back(Event::DragFinished {
+                    accepted: accepted,
+                    action: if accepted { action_from_atom(display, action) } else { None },
+                });
+            }
+
+        } else {
+            return false;
+        }
+
+        true
+    }
+
+    /// Returns how long the target of the drop has left to answer, if the data was dropped.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.outgoing.as_ref().and_then(|drag| drag.dropped).map(|dropped| {
+            let limit = Duration::from_millis(FINISH_TIMEOUT_MS);
+            let elapsed = dropped.elapsed();
+            if elapsed < limit { limit - elapsed } else { Duration::from_millis(0) }
+        })
+    }
+
+    /// Gives up on the drop if its target didn't answer in time.
+    pub fn check_timeout(&mut self) {
+        if self.timeout() == Some(Duration::from_millis(0)) {
+            warn!("[winit] The target of the drop didn't answer in time.");
+            self.abort_drop();
+        }
+    }
+
+    /// Gives up on the drop if `destroyed` is its target.
+    pub fn handle_destroy_notify(&mut self, destroyed: ffi::Window) {
+        let aborted = match self.outgoing {
+            Some(ref drag) => drag.dropped.is_some() && drag.target.map(|t| t.0) == Some(destroyed),
+            None => false,
+        };
+        if aborted {
+            self.abort_drop();
+        }
+    }
+
+    fn abort_drop(&mut self) {
+        self.outgoing = None;
+        self.events.push_back(Event::DragFinished { accepted: false, action: None });
+    }
+
+    /// Reads the list of files being dragged once the source converted `XdndSelection`, and
+    /// sends the answers that were waiting for it.
+    ///
+    /// Returns false if the event isn't the answer to a conversion of `XdndSelection`.
+    pub fn handle_selection_notify(&mut self, display: &XConnection, window: ffi::Window,
+                                   event: &ffi::XSelectionEvent) -> bool
+    {
+        if event.selection != display.get_atom("XdndSelection") {
+            return false;
+        }
+
+        // the answer may belong to a drag that already left
+        let drag = match self.incoming {
+            Some(ref mut drag) if drag.requested && drag.paths.is_none() => drag,
+            _ => return true,
+        };
+
+        // transfers in chunks aren't supported here, lists of files are small
+        let property = display.get_atom("XdndSelection");
+        let data = if event.property == property {
+            selection::read_property(display, window, property, true)
+        } else {
+            None
+        };
+        drag.paths = Some(match data {
+            Some((ty, ref data)) if ty != display.get_atom("INCR") => uri_list::parse_uri_list(data),
+            _ => Vec::new(),
+        });
+
+        if drag.dropped {
+            let drag = self.incoming.take().unwrap();
+            finish_drop(display, window, drag, &mut self.events);
+        } else if let Some(position) = drag.pending_position.take() {
+            update_position(display, window, drag, position, &mut self.events);
+        }
+
+        true
+    }
+}
+
+// Tells the source whether the drop would be accepted at `position`, which is given in window
+// coordinates.
+fn update_position(display: &XConnection, window: ffi::Window, drag: &mut IncomingDrag,
+                   position: (i32, i32), events: &mut VecDeque<Event>)
+{
+    if drag.position != Some(position) {
+        drag.position = Some(position);
+        for path in drag.paths.iter().flat_map(|paths| paths.iter()) {
+            events.push_back(Event::HoveredFile(path.clone(), position.0, position.1));
+        }
+    }
+
+    let accepted = drag.paths.as_ref().map(|paths| !paths.is_empty()).unwrap_or(false);
+    send_client_message(display, drag.source, display.get_atom("XdndStatus"), [
+        window as c_long,
+        // bit 0: the drop is accepted, bit 1: keep sending positions
+        if accepted { 3 } else { 2 },
+        // an empty rectangle asks for a message on every move
+        0,
+        0,
+        if accepted { display.get_atom("XdndActionCopy") as c_long } else { 0 },
+    ]);
+}
+
+// Delivers the dropped files and tells the source that the drop is complete.
+fn finish_drop(display: &XConnection, window: ffi::Window, drag: IncomingDrag, events: &mut VecDeque<Event>) {
+    let paths = drag.paths.unwrap_or(Vec::new());
+    let accepted = !paths.is_empty();
+    for path in paths {
+        events.push_back(Event::DroppedFile(path));
+    }
+
+    // those fields were added in XDND v5
+    let (status, action) = if accepted && drag.version >= 5 {
+        (1, display.get_atom("XdndActionCopy") as c_long)
+    } else {
+        (0, 0)
+    };
+    send_client_message(display, drag.source, display.get_atom("XdndFinished"),
+                        [window as c_long, status, action, 0, 0]);
+}
+
+// Sends the last position of the pointer to the target, if it wasn't sent yet.
+fn send_position(display: &XConnection, window: ffi::Window, drag: &mut OutgoingDrag) {
+    if let (Some((target, _)), Some((x, y, time))) = (drag.target, drag.pending_position.take()) {
+        send_client_message(display, target, display.get_atom("XdndPosition"), [
+            window as c_long,
+            0,
+            (x as c_long) << 16 | (y as c_long & 0xffff),
+            time as c_long,
+            display.get_atom("XdndActionCopy") as c_long,
+        ]);
+        drag.waiting_status = true;
+    }
+}
+
+// Returns the deepest window under the given position that supports XDND, along with
+// the version it supports.
+fn find_drop_target(display: &XConnection, x_root: libc::c_int, y_root: libc::c_int)
+                    -> Option<(ffi::Window, c_long)>
+{
+    let root = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
+    let xdnd_aware = display.get_atom("XdndAware");
+
+    let mut window = root;
+    loop {
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        unsafe {
+            (display.xlib.XTranslateCoordinates)(display.display, root, window, x_root, y_root,
+                                                 &mut x, &mut y, &mut child);
+        }
+        display.ignore_error();
+
+        if child == 0 {
+            return None;
+        }
+        window = child;
+
+        // the window may be destroyed at any time, like menus that close during the drag
+        if let Some((ty, version)) = selection::try_read_property(display, window, xdnd_aware) {
+            if ty == ffi::XA_ATOM && version.len() >= 4 {
+                let version = unsafe { ptr::read_unaligned(version.as_ptr() as *const u32) };
+                return Some((window, version as c_long));
+            }
+        }
+    }
+}
+
+fn action_from_atom(display: &XConnection, atom: ffi::Atom) -> Option<DragAction> {
+    if atom == display.get_atom("XdndActionCopy") {
+        Some(DragAction::Copy)
+    } else if atom == display.get_atom("XdndActionMove") {
+        Some(DragAction::Move)
+    } else if atom == display.get_atom("XdndActionLink") {
+        Some(DragAction::Link)
+    } else {
+        None
+    }
+}
+
+/// Advertises the window as a drop target.
+pub fn set_aware(display: &XConnection, window: ffi::Window) {
+    let version = XDND_VERSION as libc::c_ulong;
+    unsafe {
+        (display.xlib.XChangeProperty)(display.display, window, display.get_atom("XdndAware"),
+                                       ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                                       &version as *const libc::c_ulong as *const u8, 1);
+    }
+    display.check_errors().expect("Failed to set XdndAware");
+}
+
+/// Gives up on the drop in progress of our windows whose target is `destroyed`.
+pub fn handle_destroy_notify(display: &XConnection, destroyed: ffi::Window) {
+    for dnd in WINDOWS.all(display) {
+        dnd.lock().unwrap().handle_destroy_notify(destroyed);
+    }
+}
+
+// Asks the server for a `DestroyNotify` event when `target` is destroyed. Returns false if it
+// already is.
+fn watch_destruction(display: &XConnection, target: ffi::Window) -> bool {
+    // our windows already select this event, and selecting it again would replace their mask
+    if WINDOWS.find(display, target).is_some() {
+        return true;
+    }
+    unsafe {
+        (display.xlib.XSelectInput)(display.display, target, ffi::StructureNotifyMask);
+        (display.xlib.XSync)(display.display, ffi::False);
+    }
+    display.check_errors().is_ok()
+}
+
+/// Asks the source for the list of files being dragged. The answer is a `SelectionNotify` event,
+/// handled by `handle_selection_notify`.
+fn request_paths(display: &XConnection, window: ffi::Window, time: ffi::Time) {
+    let selection = display.get_atom("XdndSelection");
+    unsafe {
+        (display.xlib.XConvertSelection)(display.display, selection, display.get_atom("text/uri-list"),
+                                         selection, window, time);
+        (display.xlib.XFlush)(display.display);
+    }
+    display.check_errors().expect("Failed to call XConvertSelection");
+}
+
+/// Returns true if `event` is the answer to a conversion of `XdndSelection`. The other answers
+/// are waited for by `selection::convert_selection`, so the event loop leaves them in the queue.
+pub unsafe extern "C" fn is_xdnd_selection_notify(_: *mut ffi::Display, event: *mut ffi::XEvent,
+                                                  selection: *mut libc::c_char) -> libc::c_int
+{
+    let event = &*(event as *const ffi::XSelectionEvent);
+    (event.type_ == ffi::SelectionNotify && event.selection == selection as ffi::Atom) as libc::c_int
+}
+
+fn translate_from_root(display: &XConnection, window: ffi::Window, x: libc::c_int, y: libc::c_int)
+                       -> (i32, i32)
+{
+    let (mut window_x, mut window_y, mut child) = (0, 0, 0);
+    unsafe {
+        let root = (display.xlib.XDefaultRootWindow)(display.display);
+        (display.xlib.XTranslateCoordinates)(display.display, root, window, x, y,
+                                             &mut window_x, &mut window_y, &mut child);
+    }
+    display.check_errors().expect("Failed to call XTranslateCoordinates");
+    (window_x as i32, window_y as i32)
+}
+
+fn send_client_message(display: &XConnection, target: ffi::Window, message_type: ffi::Atom,
+                       longs: [c_long; 5])
+{
+    let mut data = ffi::ClientMessageData::new();
+    for (i, &value) in longs.iter().enumerate() {
+        data.set_long(i, value);
+    }
+
+    let client_message_event = ffi::XClientMessageEvent {
+        type_: ffi::ClientMessage,
+        serial: 0,
+        send_event: 1,
+        display: display.display,
+        window: target,
+        message_type: message_type,
+        format: 32,
+        data: data,
+    };
+    let mut x_event = ffi::XEvent::from(client_message_event);
+
+    unsafe {
+        (display.xlib.XSendEvent)(display.display, target, ffi::False, ffi::NoEventMask, &mut x_event);
+        (display.xlib.XFlush)(display.display);
+    }
+    // the other client may have disappeared in the meantime
+    display.ignore_error();
+}
@@ -5,4 +5,6 @@ pub use x11_dl::xlib::*;
 pub use x11_dl::xinput::*;
 pub use x11_dl::xinput2::*;
 pub use x11_dl::xlib_xcb::*;
+pub use x11_dl::xshm::*;
+pub use x11_dl::xpresent::*;
 pub use x11_dl::error::OpenError;
@@ -0,0 +1,124 @@
+//! Paces the `RedrawRequested` events asked for with `Window::request_redraw`.
+//!
+//! With the Present extension, the redraw happens at the next vertical blank of the monitor that
+//! shows the window. The X server slows this down to about once per second for windows that
+//! aren't shown on any monitor. Without the extension, redraws are limited to 60 per second with
+//! a timer, and stop while the window is minimized.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use libc;
+
+use Event;
+
+use super::ffi;
+use super::XConnection;
+
+// Interval between two redraws when the Present extension is missing
+const FALLBACK_INTERVAL_MS: u64 = 16;
+
+pub struct FrameClock {
+    // major opcode of the Present extension, if the server supports it
+    present_opcode: Option<libc::c_int>,
+    requested: bool,
+    // a `PresentCompleteNotify` is expected
+    waiting: bool,
+    serial: u32,
+    last_frame: Option<Instant>,
+}
+
+impl FrameClock {
+    pub fn new(display: &XConnection, window: ffi::Window) -> FrameClock {
+        let present_opcode = display.xpresent.as_ref().and_then(|xpresent| unsafe {
+            let (mut opcode, mut event_base, mut error_base) = (0, 0, 0);
+            if (xpresent.XPresentQueryExtension)(display.display, &mut opcode, &mut event_base,
+                                                 &mut error_base) == 0
+            {
+                return None;
+            }
+            (xpresent.XPresentSelectInput)(display.display, window,
+                                           ffi::PresentCompleteNotifyMask as libc::c_uint);
+            Some(opcode)
+        });
+        display.check_errors().expect("Failed to call XPresentSelectInput");
+
+        FrameClock {
+            present_opcode: present_opcode,
+            requested: false,
+            waiting: false,
+            serial: 0,
+            last_frame: None,
+        }
+    }
+
+    /// Asks for a `RedrawRequested` event at the next frame. Several requests before that frame
+    /// produce a single event.
+    pub fn request_redraw(&mut self, display: &XConnection, window: ffi::Window) {
+        self.requested = true;
+
+        if let (Some(_), Some(xpresent)) = (self.present_opcode, display.xpresent.as_ref()) {
+            if !self.waiting {
+                self.serial = self.serial.wrapping_add(1);
+                unsafe {
+                    // a divisor of 1 targets the next vertical blank
+                    (xpresent.XPresentNotifyMSC)(display.display, window, self.serial, 0, 1, 0);
+                    (display.xlib.XFlush)(display.display);
+                }
+                display.check_errors().expect("Failed to call XPresentNotifyMSC");
+                self.waiting = true;
+            }
+        }
+    }
+
+    /// Handles the event if it comes from the Present extension. Returns false otherwise.
+    pub fn handle_generic_event(&mut self, cookie: &ffi::XGenericEventCookie, window: ffi::Window,
+                                events: &mut VecDeque<Event>) -> bool
+    {
+        if self.present_opcode != Some(cookie.extension) {
+            return false;
+        }
+
+        if cookie.evtype == ffi::PresentCompleteNotify {
+            let complete = unsafe { &*(cookie.data as *const ffi::XPresentCompleteNotifyEvent) };
+            if complete.window == window && complete.serial_number == self.serial {
+                self.waiting = false;
+                if self.requested {
+                    self.requested = false;
+                    events.push_back(Event::RedrawRequested);
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Without the Present extension, produces the requested redraw if it is due.
+    pub fn poll_timer(&mut self, visible: bool, events: &mut VecDeque<Event>) -> bool {
+        match self.timeout(visible) {
+            Some(timeout) if timeout == Duration::from_millis(0) => {
+                self.requested = false;
+                self.last_frame = Some(Instant::now());
+                events.push_back(Event::RedrawRequested);
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// Without the Present extension, returns how long the event loop can wait before the
+    /// requested redraw is due.
+    pub fn timeout(&self, visible: bool) -> Option<Duration> {
+        if self.present_opcode.is_some() || !self.requested || !visible {
+            return None;
+        }
+
+        let interval = Duration::from_millis(FALLBACK_INTERVAL_MS);
+        let elapsed = self.last_frame.map(|last_frame| last_frame.elapsed()).unwrap_or(interval);
+        if elapsed < interval {
+            Some(interval - elapsed)
+        } else {
+            Some(Duration::from_millis(0))
+        }
+    }
+}
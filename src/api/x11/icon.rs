@@ -0,0 +1,151 @@
+//! Window icons, given to the window manager as `_NET_WM_ICON` and, for the older window
+//! managers, as the pixmaps of `WM_HINTS`.
+
+use std::ptr;
+use std::sync::Arc;
+
+use libc;
+
+use {Icon, PixelFormat};
+
+use super::ffi;
+use super::software::{copy_pixels, native_byte_order};
+use super::XConnection;
+
+// flags of `XWMHints`
+const ICON_PIXMAP_HINT: libc::c_long = 1 << 2;
+const ICON_MASK_HINT: libc::c_long = 1 << 5;
+
+// The pixmaps are made from the image closest to this size, which is the usual one of icons in
+// the window managers that use them
+const PIXMAP_ICON_SIZE: i64 = 32;
+
+/// The pixmaps of the icon in `WM_HINTS`, which must live as long as the hints refer to them.
+pub struct IconPixmaps {
+    display: Arc<XConnection>,
+    pixmap: ffi::Pixmap,
+    mask: ffi::Pixmap,
+}
+
+impl Drop for IconPixmaps {
+    fn drop(&mut self) {
+        unsafe {
+            (self.display.xlib.XFreePixmap)(self.display.display, self.pixmap);
+            (self.display.xlib.XFreePixmap)(self.display.display, self.mask);
+        }
+    }
+}
+
+/// Sets the icon of `window`, or removes it if `icon` is empty.
+pub fn set_icon(display: &Arc<XConnection>, window: ffi::Window, screen_id: libc::c_int, icon: &[Icon])
+                -> Option<IconPixmaps>
+{
+    set_net_wm_icon(display, window, icon);
+
+    let pixmaps = icon.iter()
+                      .filter(|image| image.width != 0 && image.height != 0)
+                      .min_by_key(|image| (image.width as i64 - PIXMAP_ICON_SIZE).abs())
+                      .map(|image| create_pixmaps(display, window, screen_id, image));
+    set_wm_hints(display, window, pixmaps.as_ref());
+    display.check_errors().expect("Failed to set the icon pixmaps");
+    pixmaps
+}
+
+// The images are written one after the other, each as its width, its height and its pixels in
+// `0xAARRGGBB` values, without premultiplied alpha.
+fn set_net_wm_icon(display: &XConnection, window: ffi::Window, icon: &[Icon]) {
+    let net_wm_icon = display.get_atom("_NET_WM_ICON");
+    unsafe {
+        if icon.is_empty() {
+            (display.xlib.XDeleteProperty)(display.display, window, net_wm_icon);
+        } else {
+            let mut data: Vec<libc::c_ulong> = Vec::new();
+            for image in icon {
+                data.push(image.width as libc::c_ulong);
+                data.push(image.height as libc::c_ulong);
+                let len = image.width as usize * image.height as usize * 4;
+                data.extend(image.rgba[.. len].chunks(4).map(|pixel| {
+                    (pixel[3] as libc::c_ulong) << 24 | (pixel[0] as libc::c_ulong) << 16 |
+                    (pixel[1] as libc::c_ulong) << 8 | pixel[2] as libc::c_ulong
+                }));
+            }
+            // properties of format 32 are passed as an array of longs
+            (display.xlib.XChangeProperty)(display.display, window, net_wm_icon, ffi::XA_CARDINAL,
+                                           32, ffi::PropModeReplace, data.as_ptr() as *const u8,
+                                           data.len() as libc::c_int);
+        }
+    }
+    display.check_errors().expect("Failed to set _NET_WM_ICON");
+}
+
+// The pixmap has the depth of the root window, and the mask hides the pixels that are mostly
+// transparent.
+fn create_pixmaps(display: &Arc<XConnection>, window: ffi::Window, screen_id: libc::c_int, image: &Icon)
+                  -> IconPixmaps
+{
+    let (width, height) = (image.width, image.height);
+    unsafe {
+        let visual = (display.xlib.XDefaultVisual)(display.display, screen_id);
+        let depth = (display.xlib.XDefaultDepth)(display.display, screen_id) as libc::c_uint;
+        let pixmap = (display.xlib.XCreatePixmap)(display.display, window, width, height, depth);
+
+        let ximage = (display.xlib.XCreateImage)(display.display, visual, depth, ffi::ZPixmap, 0,
+                                                 ptr::null_mut(), width, height, 32, 0);
+        display.check_errors().expect("Failed to call XCreateImage");
+        assert!(!ximage.is_null(), "Failed to call XCreateImage");
+        (*ximage).byte_order = native_byte_order();
+        // freed by `XDestroyImage`
+        let size = (*ximage).bytes_per_line as usize * height as usize;
+        (*ximage).data = libc::calloc(size, 1) as *mut libc::c_char;
+        copy_pixels(&display.xlib, ximage, &image.rgba, width, PixelFormat::Rgba8, 0, 0, width, height);
+
+        let gc = (display.xlib.XCreateGC)(display.display, pixmap, 0, ptr::null_mut());
+        (display.xlib.XPutImage)(display.display, pixmap, gc, ximage, 0, 0, 0, 0, width, height);
+        (display.xlib.XFreeGC)(display.display, gc);
+        (display.xlib.XDestroyImage)(ximage);
+
+        // one bit per pixel, the least significant first, and rows padded to whole bytes
+        let stride = (width as usize + 7) / 8;
+        let mut bits = vec![0u8; stride * height as usize];
+        for (index, pixel) in image.rgba.chunks(4).take(width as usize * height as usize).enumerate() {
+            let (x, y) = (index % width as usize, index / width as usize);
+            if pixel[3] >= 128 {
+                bits[y * stride + x / 8] |= 1 << (x % 8);
+            }
+        }
+        let mask = (display.xlib.XCreateBitmapFromData)(display.display, window,
+                                                        bits.as_ptr() as *const libc::c_char,
+                                                        width, height);
+
+        IconPixmaps {
+            display: display.clone(),
+            pixmap: pixmap,
+            mask: mask,
+        }
+    }
+}
+
+fn set_wm_hints(display: &XConnection, window: ffi::Window, pixmaps: Option<&IconPixmaps>) {
+    unsafe {
+        // the other hints are kept
+        let mut hints = (display.xlib.XGetWMHints)(display.display, window);
+        if hints.is_null() {
+            hints = (display.xlib.XAllocWMHints)();
+            if hints.is_null() {
+                return;
+            }
+        }
+        match pixmaps {
+            Some(pixmaps) => {
+                (*hints).flags |= ICON_PIXMAP_HINT | ICON_MASK_HINT;
+                (*hints).icon_pixmap = pixmaps.pixmap;
+                (*hints).icon_mask = pixmaps.mask;
+            },
+            None => {
+                (*hints).flags &= !(ICON_PIXMAP_HINT | ICON_MASK_HINT);
+            },
+        }
+        (display.xlib.XSetWMHints)(display.display, window, hints);
+        (display.xlib.XFree)(hints as *mut _);
+    }
+}
@@ -0,0 +1,163 @@
+//! The properties through which window managers, taskbars and session managers identify the
+//! application a window belongs to, and the startup notification of the launcher.
+
+use std::env;
+use std::ffi::CStr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, ATOMIC_BOOL_INIT, Ordering};
+
+use libc;
+
+use super::ffi;
+use super::XConnection;
+
+// flag of `XWMHints`
+const URGENCY_HINT: libc::c_long = 1 << 8;
+
+/// Returns the `WM_CLASS` of the windows that weren't given one. As recommended by the ICCCM,
+/// the instance name is `RESOURCE_NAME` or the name of the executable, and the class name is the
+/// instance name capitalized.
+pub fn default_class() -> (String, String) {
+    let instance = env::var("RESOURCE_NAME").ok()
+        .or_else(|| {
+            env::args_os().next()
+                .and_then(|arg| Path::new(&arg).file_name().map(|name| name.to_string_lossy().into_owned()))
+        })
+        .unwrap_or_else(|| "winit".to_owned());
+
+    let mut chars = instance.chars();
+    let class = match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    };
+    (instance, class)
+}
+
+pub fn set_class(display: &XConnection, window: ffi::Window, instance: &CStr, class: &CStr) {
+    unsafe {
+        let hint = (display.xlib.XAllocClassHint)();
+        if hint.is_null() {
+            return;
+        }
+        (*hint).res_name = instance.as_ptr() as *mut libc::c_char;
+        (*hint).res_class = class.as_ptr() as *mut libc::c_char;
+        (display.xlib.XSetClassHint)(display.display, window, hint);
+        display.check_errors().expect("Failed to call XSetClassHint");
+        (display.xlib.XFree)(hint as *mut _);
+    }
+}
+
+/// Sets `_NET_WM_PID`, `WM_CLIENT_MACHINE` and `WM_CLIENT_LEADER`. Each window is its own
+/// client leader, as they don't share a session.
+pub fn set_client_properties(display: &XConnection, window: ffi::Window) {
+    unsafe {
+        // properties of format 32 are passed as an array of longs
+        let pid = libc::getpid() as libc::c_ulong;
+        (display.xlib.XChangeProperty)(display.display, window, display.get_atom("_NET_WM_PID"),
+                                       ffi::XA_CARDINAL, 32, ffi::PropModeReplace,
+                                       &pid as *const libc::c_ulong as *const u8, 1);
+
+        // the pid only means something on the machine that runs the process
+        let mut hostname = [0u8; 256];
+        if libc::gethostname(hostname.as_mut_ptr() as *mut libc::c_char, hostname.len()) == 0 {
+            let len = hostname.iter().position(|&c| c == 0).unwrap_or(hostname.len());
+            (display.xlib.XChangeProperty)(display.display, window, ffi::XA_WM_CLIENT_MACHINE,
+                                           ffi::XA_STRING, 8, ffi::PropModeReplace,
+                                           hostname.as_ptr(), len as libc::c_int);
+        }
+
+        let leader = window as libc::c_ulong;
+        (display.xlib.XChangeProperty)(display.display, window, display.get_atom("WM_CLIENT_LEADER"),
+                                       ffi::XA_WINDOW, 32, ffi::PropModeReplace,
+                                       &leader as *const libc::c_ulong as *const u8, 1);
+    }
+    display.check_errors().expect("Failed to set the client properties");
+}
+
+/// Sets or clears the urgency flag of `WM_HINTS`, keeping the other hints.
+pub fn set_urgency(display: &XConnection, window: ffi::Window, urgent: bool) {
+    unsafe {
+        let mut hints = (display.xlib.XGetWMHints)(display.display, window);
+        if hints.is_null() {
+            hints = (display.xlib.XAllocWMHints)();
+            if hints.is_null() {
+                return;
+            }
+        }
+        if urgent {
+            (*hints).flags |= URGENCY_HINT;
+        } else {
+            (*hints).flags &= !URGENCY_HINT;
+        }
+        (display.xlib.XSetWMHints)(display.display, window, hints);
+        (display.xlib.XFree)(hints as *mut _);
+    }
+    display.check_errors().expect("Failed to set the urgency hint");
+}
+
+// whether a window already took the startup notification id
+static STARTUP_ID_TAKEN: AtomicBool = ATOMIC_BOOL_INIT;
+
+/// Returns the startup notification id given by the launcher of the application, which only the
+/// first window takes. It is left in the environment, which belongs to the host when we are
+/// loaded as a plugin, and which other threads may be reading.
+pub fn take_startup_id() -> Option<String> {
+    if STARTUP_ID_TAKEN.swap(true, Ordering::SeqCst) {
+        return None;
+    }
+    env::var("DESKTOP_STARTUP_ID").ok().and_then(|id| if id.is_empty() { None } else { Some(id) })
+}
+
+/// Sets `_NET_STARTUP_ID`, which the window manager reads when the window gets mapped.
+pub fn set_startup_id(display: &XConnection, window: ffi::Window, id: &str) {
+    unsafe {
+        (display.xlib.XChangeProperty)(display.display, window, display.get_atom("_NET_STARTUP_ID"),
+                                       display.get_atom("UTF8_STRING"), 8, ffi::PropModeReplace,
+                                       id.as_ptr(), id.len() as libc::c_int);
+    }
+    display.check_errors().expect("Failed to set _NET_STARTUP_ID");
+}
+
+/// Tells the launcher that the startup identified by `id` is complete, once the window has been
+/// mapped.
+pub fn complete_startup(display: &XConnection, window: ffi::Window, id: &str) {
+    let mut message = String::from("remove: ID=\"");
+    for c in id.chars() {
+        if c == '"' || c == '\\' {
+            message.push('\\');
+        }
+        message.push(c);
+    }
+    message.push('"');
+    let mut message = message.into_bytes();
+    message.push(0);
+
+    let root = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
+    let begin = display.get_atom("_NET_STARTUP_INFO_BEGIN");
+    let more = display.get_atom("_NET_STARTUP_INFO");
+
+    // the message is split into chunks of 20 bytes, the first one of which has its own type
+    for (index, chunk) in message.chunks(20).enumerate() {
+        let mut data = ffi::ClientMessageData::new();
+        for (i, &byte) in chunk.iter().enumerate() {
+            data.set_byte(i, byte as libc::c_char);
+        }
+        let client_message_event = ffi::XClientMessageEvent {
+            type_: ffi::ClientMessage,
+            serial: 0,
+            send_event: 1,
+            display: display.display,
+            window: window,
+            message_type: if index == 0 { begin } else { more },
+            format: 8,
+            data: data,
+        };
+        let mut x_event = ffi::XEvent::from(client_message_event);
+        unsafe {
+            (display.xlib.XSendEvent)(display.display, root, 0, ffi::PropertyChangeMask,
+                                      &mut x_event as *mut _);
+        }
+    }
+    unsafe { (display.xlib.XFlush)(display.display) };
+    display.check_errors().expect("Failed to send _NET_STARTUP_INFO");
+}
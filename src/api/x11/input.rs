@@ -40,7 +40,11 @@ struct InputState {
     cursor_pos: (f64, f64),
     /// Last-seen positions of axes, used to report delta
     /// movements when a new absolute axis value is received
-    axis_values: Vec<AxisValue>
+    axis_values: Vec<AxisValue>,
+    /// Keys currently held down, indexed by keycode. With
+    /// detectable auto-repeat, a key that is held down is pressed
+    /// again without being released
+    keys_down: [bool; 256]
 }
 
 pub struct XInputEventHandler {
@@ -116,13 +120,14 @@ impl XInputEventHandler {
             axis_list: read_input_axis_info(display),
             current_state: InputState {
                 cursor_pos: (0.0, 0.0),
-                axis_values: Vec::new()
+                axis_values: Vec::new(),
+                keys_down: [false; 256]
             },
             multitouch: window_attrs.multitouch,
         }
     }
 
-    pub fn translate_key_event(&self, event: &mut ffi::XKeyEvent) -> Vec<Event> {
+    pub fn translate_key_event(&mut self, event: &mut ffi::XKeyEvent) -> Vec<Event> {
         use events::Event::{KeyboardInput, ReceivedCharacter};
         use events::ElementState::{Pressed, Released};
 
@@ -165,7 +170,11 @@ impl XInputEventHandler {
 
         let vkey = events::keycode_to_element(keysym as libc::c_uint);
 
-        translated_events.push(KeyboardInput(state, event.keycode as u8, vkey));
+        let key_down = &mut self.current_state.keys_down[event.keycode as u8 as usize];
+        let is_repeat = state == Pressed && *key_down;
+        *key_down = state == Pressed;
+
+        translated_events.push(KeyboardInput(state, event.keycode as u8, vkey, is_repeat));
         translated_events
     }
 
@@ -258,7 +267,11 @@ impl XInputEventHandler {
             },
             ffi::XI_Leave => Some(MouseLeft),
             ffi::XI_FocusIn => Some(Focused(true)),
-            ffi::XI_FocusOut => Some(Focused(false)),
+            ffi::XI_FocusOut => {
+                // the keys released while the window isn't focused aren't reported
+                self.current_state.keys_down = [false; 256];
+                Some(Focused(false))
+            },
             ffi::XI_TouchBegin | ffi::XI_TouchUpdate | ffi::XI_TouchEnd => {
                 if !self.multitouch {
                     return None
@@ -6,8 +6,16 @@ pub use self::xdisplay::{XConnection, XNotSupported, XError};
 
 pub mod ffi;
 
+mod cursor;
+mod dnd;
 mod events;
+mod frame_clock;
+mod icon;
+mod identity;
 mod input;
 mod monitor;
+mod registry;
+mod selection;
+mod software;
 mod window;
 mod xdisplay;
@@ -0,0 +1,47 @@
+//! Lookup of the state of our windows from their id.
+//!
+//! The windows of a connection share its event queue, so the events of one of them may be read
+//! by the event loop of another one, which then handles them in its place.
+
+use std::sync::{Arc, Mutex, Weak};
+
+use super::ffi;
+use super::XConnection;
+
+/// The states of type `T` of our windows, along with the connection and the window they belong
+/// to. The states are only kept while their window holds them.
+pub struct Registry<T> {
+    windows: Mutex<Vec<(usize, ffi::Window, Weak<Mutex<T>>)>>,
+}
+
+impl<T> Registry<T> {
+    pub fn new() -> Registry<T> {
+        Registry {
+            windows: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Stores the state of `window`, which `find` returns as long as it is alive.
+    pub fn register(&self, display: &XConnection, window: ffi::Window, state: T) -> Arc<Mutex<T>> {
+        let state = Arc::new(Mutex::new(state));
+        let mut windows = self.windows.lock().unwrap();
+        // the id of a destroyed window may be reused
+        windows.retain(|&(_, _, ref weak)| weak.upgrade().is_some());
+        windows.push((display.display as usize, window, Arc::downgrade(&state)));
+        state
+    }
+
+    /// Returns the state of `window` if it is one of our windows.
+    pub fn find(&self, display: &XConnection, window: ffi::Window) -> Option<Arc<Mutex<T>>> {
+        let windows = self.windows.lock().unwrap();
+        windows.iter().filter(|&&(d, w, _)| d == display.display as usize && w == window)
+                      .filter_map(|&(_, _, ref weak)| weak.upgrade()).next()
+    }
+
+    /// Returns the states of all our windows on the connection.
+    pub fn all(&self, display: &XConnection) -> Vec<Arc<Mutex<T>>> {
+        let windows = self.windows.lock().unwrap();
+        windows.iter().filter(|&&(d, _, _)| d == display.display as usize)
+                      .filter_map(|&(_, _, ref weak)| weak.upgrade()).collect()
+    }
+}
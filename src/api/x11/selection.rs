@@ -0,0 +1,372 @@
+//! Support for the X11 selections, following the ICCCM.
+//!
+//! The owner of a selection answers `SelectionRequest` events by writing the data to a property
+//! of the requestor window. Data that doesn't fit in a single request is sent in chunks with
+//! the `INCR` mechanism.
+
+use std::{cmp, mem, ptr, thread};
+use std::ffi::CStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use libc;
+
+use {ClipboardData, Selection};
+
+use super::ffi;
+use super::{XConnection, XError};
+use super::registry::Registry;
+
+// Data bigger than this is sent with `INCR`. This is well below the maximum request size that
+// every X server supports.
+const INCR_CHUNK_SIZE: usize = 64 * 1024;
+
+// How long to wait for the owner of a selection before giving up.
+const CONVERT_TIMEOUT_MS: u64 = 1000;
+
+lazy_static! {
+    // the requests for the selections of a window may be read by another one
+    static ref OWNERS: Registry<Selections> = Registry::new();
+}
+
+/// Returns the atom identifying `selection`.
+pub fn selection_atom(display: &XConnection, selection: Selection) -> ffi::Atom {
+    match selection {
+        Selection::Clipboard => display.get_atom("CLIPBOARD"),
+        Selection::Primary => ffi::XA_PRIMARY,
+    }
+}
+
+/// An `INCR` transfer to another client, waiting for it to delete the property before the next
+/// chunk is sent.
+struct IncrTransfer {
+    requestor: ffi::Window,
+    property: ffi::Atom,
+    target: ffi::Atom,
+    data: Vec<u8>,
+    offset: usize,
+}
+
+/// The selections owned by a window, and the transfers in progress.
+pub struct Selections {
+    owned: Vec<(ffi::Atom, Vec<ClipboardData>)>,
+    transfers: Vec<IncrTransfer>,
+}
+
+impl Selections {
+    /// Creates the selections of `window`, which `find_owner` returns as long as they are alive.
+    pub fn register(display: &XConnection, window: ffi::Window) -> Arc<Mutex<Selections>> {
+        OWNERS.register(display, window, Selections {
+            owned: Vec::new(),
+            transfers: Vec::new(),
+        })
+    }
+
+    /// Stores the contents of a selection that the window now owns.
+    pub fn set(&mut self, selection: ffi::Atom, contents: Vec<ClipboardData>) {
+        self.clear(selection);
+        self.owned.push((selection, contents));
+    }
+
+    /// Returns the contents of a selection owned by the window.
+    pub fn get(&self, selection: ffi::Atom) -> Option<&[ClipboardData]> {
+        self.owned.iter().find(|&&(atom, _)| atom == selection).map(|&(_, ref contents)| &contents[..])
+    }
+
+    /// Forgets the contents of a selection, after another client took it over.
+    pub fn clear(&mut self, selection: ffi::Atom) {
+        self.owned.retain(|&(atom, _)| atom != selection);
+    }
+
+    /// Answers a request from another client for the contents of a selection.
+    pub fn handle_request(&mut self, display: &XConnection, request: &ffi::XSelectionRequestEvent) {
+        let targets = display.get_atom("TARGETS");
+        let incr = display.get_atom("INCR");
+
+        // obsolete clients don't specify a property
+        let property = if request.property == 0 { request.target } else { request.property };
+
+        let success = match self.get(request.selection) {
+            None => false,
+            Some(contents) => unsafe {
+                if request.target == targets {
+                    let mut atoms = vec![targets];
+                    atoms.extend(contents.iter().map(|content| display.get_atom(&content.mime_type)));
+                    (display.xlib.XChangeProperty)(display.display, request.requestor, property,
+                                                   ffi::XA_ATOM, 32, ffi::PropModeReplace,
+                                                   atoms.as_ptr() as *const u8,
+                                                   atoms.len() as libc::c_int);
+                    true
+                } else if let Some(content) = contents.iter()
+                                                      .find(|c| display.get_atom(&c.mime_type) == request.target)
+                {
+                    if content.data.len() > INCR_CHUNK_SIZE {
+                        // the data is sent once the requestor deletes the property
+                        (display.xlib.XSelectInput)(display.display, request.requestor,
+                                                    ffi::PropertyChangeMask);
+                        let size = content.data.len() as libc::c_ulong;
+                        (display.xlib.XChangeProperty)(display.display, request.requestor, property,
+                                                       incr, 32, ffi::PropModeReplace,
+                                                       &size as *const libc::c_ulong as *const u8, 1);
+                        self.transfers.push(IncrTransfer {
+                            requestor: request.requestor,
+                            property: property,
+                            target: request.target,
+                            data: content.data.clone(),
+                            offset: 0,
+                        });
+                    } else {
+                        (display.xlib.XChangeProperty)(display.display, request.requestor, property,
+                                                       request.target, 8, ffi::PropModeReplace,
+                                                       content.data.as_ptr(),
+                                                       content.data.len() as libc::c_int);
+                    }
+                    true
+                } else {
+                    false
+                }
+            },
+        };
+
+        let notify = ffi::XSelectionEvent {
+            type_: ffi::SelectionNotify,
+            serial: 0,
+            send_event: 1,
+            display: display.display,
+            requestor: request.requestor,
+            selection: request.selection,
+            target: request.target,
+            // a property of `None` tells the requestor that the conversion failed
+            property: if success { property } else { 0 },
+            time: request.time,
+        };
+        let mut x_event = ffi::XEvent::from(notify);
+
+        unsafe {
+            (display.xlib.XSendEvent)(display.display, request.requestor, ffi::False, 0, &mut x_event);
+            // the requestor may have been destroyed in the meantime, which isn't our problem
+            (display.xlib.XSync)(display.display, ffi::False);
+        }
+        display.ignore_error();
+    }
+
+    /// Sends the next chunk of an `INCR` transfer if `event` is the deletion of its property.
+    ///
+    /// Returns false if the event doesn't concern any transfer.
+    pub fn handle_property_notify(&mut self, display: &XConnection, event: &ffi::XPropertyEvent) -> bool {
+        if event.state != ffi::PropertyDelete {
+            return false;
+        }
+
+        let position = self.transfers.iter().position(|t| t.requestor == event.window &&
+                                                          t.property == event.atom);
+        let index = match position {
+            Some(index) => index,
+            None => return false,
+        };
+
+        let finished = {
+            let transfer = &mut self.transfers[index];
+            let end = cmp::min(transfer.offset + INCR_CHUNK_SIZE, transfer.data.len());
+            let chunk = &transfer.data[transfer.offset .. end];
+
+            // an empty chunk marks the end of the transfer
+            unsafe {
+                (display.xlib.XChangeProperty)(display.display, transfer.requestor, transfer.property,
+                                               transfer.target, 8, ffi::PropModeReplace,
+                                               chunk.as_ptr(), chunk.len() as libc::c_int);
+                (display.xlib.XSync)(display.display, ffi::False);
+            }
+            display.ignore_error();
+
+            transfer.offset = end;
+            chunk.is_empty()
+        };
+
+        if finished {
+            self.transfers.remove(index);
+        }
+        true
+    }
+}
+
+/// Returns the selections of `window` if it is one of our windows.
+pub fn find_owner(display: &XConnection, window: ffi::Window) -> Option<Arc<Mutex<Selections>>> {
+    OWNERS.find(display, window)
+}
+
+/// Returns the selections of the window of ours that owns `selection`, if any.
+pub fn find_selection_owner(display: &XConnection, selection: ffi::Atom) -> Option<Arc<Mutex<Selections>>> {
+    let owner = unsafe { (display.xlib.XGetSelectionOwner)(display.display, selection) };
+    display.check_errors().expect("Failed to call XGetSelectionOwner");
+    if owner == 0 {
+        return None;
+    }
+    find_owner(display, owner)
+}
+
+/// Sends the next chunk of the `INCR` transfer of any of our windows if `event` is the deletion
+/// of its property. The requestor is another client, so the event isn't tied to our windows.
+pub fn handle_property_notify(display: &XConnection, event: &ffi::XPropertyEvent) -> bool {
+    OWNERS.all(display).iter().any(|selections| {
+        selections.lock().unwrap().handle_property_notify(display, event)
+    })
+}
+
+/// Asks the owner of `selection` to convert it to `target`, and waits for the result.
+///
+/// Returns the type of the data along with the data. 16 and 32 bits items are converted to
+/// native-endian `u16`s and `u32`s.
+pub fn convert_selection(display: &XConnection, window: ffi::Window, selection: ffi::Atom,
+                         target: ffi::Atom, time: ffi::Time) -> Option<(ffi::Atom, Vec<u8>)>
+{
+    let property = display.get_atom("WINIT_SELECTION");
+    let incr = display.get_atom("INCR");
+
+    unsafe {
+        (display.xlib.XConvertSelection)(display.display, selection, target, property, window, time);
+        (display.xlib.XFlush)(display.display);
+    }
+    display.check_errors().expect("Failed to call XConvertSelection");
+
+    // wait for the owner to answer
+    let start = Instant::now();
+    let notify: ffi::XSelectionEvent = loop {
+        let mut xev: ffi::XEvent = unsafe { mem::zeroed() };
+        let found = unsafe {
+            (display.xlib.XCheckTypedWindowEvent)(display.display, window, ffi::SelectionNotify, &mut xev)
+        };
+        if found != 0 {
+            let xsel: &ffi::XSelectionEvent = unsafe { mem::transmute(&xev) };
+            break *xsel;
+        }
+        if start.elapsed() > Duration::from_millis(CONVERT_TIMEOUT_MS) {
+            return None;
+        }
+        thread::sleep(Duration::from_millis(1));
+    };
+
+    // the owner couldn't convert the selection
+    if notify.property == 0 {
+        return None;
+    }
+
+    let (ty, data) = match read_property(display, window, property, true) {
+        Some(result) => result,
+        None => return None,
+    };
+
+    if ty != incr {
+        return Some((ty, data));
+    }
+
+    // deleting the `INCR` property started the transfer, now every chunk is read and deleted
+    // until an empty one arrives
+    let mut result = Vec::new();
+    let mut result_type = 0;
+    let mut last_chunk = Instant::now();
+    loop {
+        match read_property(display, window, property, true) {
+            Some((_, ref chunk)) if chunk.is_empty() => return Some((result_type, result)),
+            Some((ty, chunk)) => {
+                result_type = ty;
+                result.extend(chunk);
+                last_chunk = Instant::now();
+            },
+            None => {
+                if last_chunk.elapsed() > Duration::from_millis(CONVERT_TIMEOUT_MS) {
+                    return None;
+                }
+                thread::sleep(Duration::from_millis(1));
+            },
+        }
+    }
+}
+
+/// Returns the names of the formats that the owner of `selection` can convert it to.
+pub fn get_targets(display: &XConnection, window: ffi::Window, selection: ffi::Atom) -> Vec<String> {
+    let targets = display.get_atom("TARGETS");
+    let data = match convert_selection(display, window, selection, targets, ffi::CurrentTime) {
+        Some((ty, data)) if ty == ffi::XA_ATOM => data,
+        _ => return Vec::new(),
+    };
+
+    data.chunks(4).filter(|chunk| chunk.len() == 4).filter_map(|chunk| {
+        let atom = unsafe { ptr::read_unaligned(chunk.as_ptr() as *const u32) } as ffi::Atom;
+        let name = unsafe { (display.xlib.XGetAtomName)(display.display, atom) };
+        display.ignore_error();
+        if name.is_null() {
+            return None;
+        }
+        let string = unsafe { CStr::from_ptr(name) }.to_string_lossy().into_owned();
+        unsafe { (display.xlib.XFree)(name as *mut _) };
+        Some(string)
+    }).filter(|name| name != "TARGETS" && name != "MULTIPLE" && name != "TIMESTAMP").collect()
+}
+
+/// Reads a property of `window`, and deletes it if `delete` is true.
+///
+/// Returns `None` if the property doesn't exist.
+pub fn read_property(display: &XConnection, window: ffi::Window, property: ffi::Atom, delete: bool)
+                     -> Option<(ffi::Atom, Vec<u8>)>
+{
+    get_property(display, window, property, delete).expect("Failed to call XGetWindowProperty")
+}
+
+/// Reads a property of a window of another client, which may be destroyed at any time.
+///
+/// Returns `None` if the property or the window doesn't exist.
+pub fn try_read_property(display: &XConnection, window: ffi::Window, property: ffi::Atom)
+                         -> Option<(ffi::Atom, Vec<u8>)>
+{
+    get_property(display, window, property, false).unwrap_or(None)
+}
+
+fn get_property(display: &XConnection, window: ffi::Window, property: ffi::Atom, delete: bool)
+                -> Result<Option<(ffi::Atom, Vec<u8>)>, XError>
+{
+    unsafe {
+        let mut actual_type = 0;
+        let mut actual_format = 0;
+        let mut nitems = 0;
+        let mut bytes_after = 0;
+        let mut data: *mut libc::c_uchar = ptr::null_mut();
+
+        // the length is expressed in 32 bits units
+        let result = (display.xlib.XGetWindowProperty)(
+            display.display, window, property, 0, libc::c_long::max_value() / 4,
+            if delete { ffi::True } else { ffi::False },
+            ffi::AnyPropertyType as ffi::Atom, &mut actual_type, &mut actual_format, &mut nitems,
+            &mut bytes_after, &mut data
+        );
+        try!(display.check_errors());
+
+        if result != ffi::Success as libc::c_int {
+            return Ok(None);
+        }
+
+        let mut bytes = Vec::new();
+        if !data.is_null() {
+            // 16 and 32 bits properties are returned as arrays of `c_short`s and `c_long`s
+            match actual_format {
+                8 => bytes.extend_from_slice(::std::slice::from_raw_parts(data, nitems as usize)),
+                16 => for i in 0 .. nitems as isize {
+                    let item = *(data as *const libc::c_ushort).offset(i) as u16;
+                    bytes.extend_from_slice(&item.to_ne_bytes());
+                },
+                32 => for i in 0 .. nitems as isize {
+                    let item = *(data as *const libc::c_ulong).offset(i) as u32;
+                    bytes.extend_from_slice(&item.to_ne_bytes());
+                },
+                _ => ()
+            }
+            (display.xlib.XFree)(data as *mut _);
+        }
+
+        if actual_type == 0 {
+            Ok(None)
+        } else {
+            Ok(Some((actual_type, bytes)))
+        }
+    }
+}
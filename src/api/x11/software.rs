@@ -0,0 +1,295 @@
+//! Presentation of pixels drawn on the CPU.
+//!
+//! The pixels are copied to an `XImage` and sent with `XPutImage`. When the X server runs on the
+//! same machine and supports MIT-SHM, the image is shared with the server instead, which avoids
+//! sending every frame through the connection.
+
+use std::{cmp, mem, ptr};
+use std::sync::Arc;
+
+use libc;
+
+use {PixelFormat, Rect};
+
+use super::ffi;
+use super::XConnection;
+
+/// An image that holds the pixels of the window, along with what is needed to present it.
+pub struct SoftwareSurface {
+    display: Arc<XConnection>,
+    gc: ffi::GC,
+    visual: *mut ffi::Visual,
+    depth: libc::c_int,
+    // set to false once attaching a shared segment failed, for example with a remote server
+    use_shm: bool,
+    image: Option<Image>,
+}
+
+struct Image {
+    ximage: *mut ffi::XImage,
+    width: u32,
+    height: u32,
+    // the server keeps a pointer to this structure through `ximage`
+    shm: Option<Box<ffi::XShmSegmentInfo>>,
+    // the server may still be reading the shared image
+    busy: bool,
+}
+
+impl SoftwareSurface {
+    pub fn new(display: &Arc<XConnection>, window: ffi::Window) -> SoftwareSurface {
+        let (visual, depth, gc) = unsafe {
+            let mut attributes: ffi::XWindowAttributes = mem::zeroed();
+            (display.xlib.XGetWindowAttributes)(display.display, window, &mut attributes);
+            let gc = (display.xlib.XCreateGC)(display.display, window, 0, ptr::null_mut());
+            (attributes.visual, attributes.depth, gc)
+        };
+        display.check_errors().expect("Failed to create a graphics context");
+
+        let use_shm = match display.xext {
+            Some(ref xext) => unsafe { (xext.XShmQueryExtension)(display.display) != 0 },
+            None => false,
+        };
+
+        SoftwareSurface {
+            display: display.clone(),
+            gc: gc,
+            visual: visual,
+            depth: depth,
+            use_shm: use_shm,
+            image: None,
+        }
+    }
+
+    /// Copies `pixels` to the window. Only the `damage` rectangles are updated, unless the
+    /// size of the buffer changed.
+    pub fn present(&mut self, window: ffi::Window, pixels: &[u8], width: u32, height: u32,
+                   format: PixelFormat, damage: Option<&[Rect]>)
+    {
+        if width == 0 || height == 0 {
+            return;
+        }
+
+        let reallocated = match self.image {
+            Some(ref image) => image.width != width || image.height != height,
+            None => true,
+        };
+        if reallocated {
+            self.destroy_image();
+            self.image = Some(self.create_image(width, height));
+        }
+
+        let whole = [Rect { x: 0, y: 0, width: width, height: height }];
+        let damage = match damage {
+            Some(damage) if !reallocated => damage,
+            _ => &whole[..],
+        };
+
+        let display = &self.display;
+        let image = self.image.as_mut().unwrap();
+
+        // the shared memory can't be written while the server reads it
+        if image.busy {
+            unsafe { (display.xlib.XSync)(display.display, ffi::False) };
+            image.busy = false;
+        }
+
+        for rect in damage {
+            let (x, y, w, h) = match clip(rect, width, height) {
+                Some(clipped) => clipped,
+                None => continue,
+            };
+            unsafe {
+                copy_pixels(&display.xlib, image.ximage, pixels, width, format, x, y, w, h);
+
+                match (image.shm.as_ref(), display.xext.as_ref()) {
+                    (Some(_), Some(xext)) => {
+                        (xext.XShmPutImage)(display.display, window, self.gc, image.ximage,
+                                            x as libc::c_int, y as libc::c_int,
+                                            x as libc::c_int, y as libc::c_int, w, h, ffi::False);
+                        image.busy = true;
+                    },
+                    _ => {
+                        (display.xlib.XPutImage)(display.display, window, self.gc, image.ximage,
+                                                 x as libc::c_int, y as libc::c_int,
+                                                 x as libc::c_int, y as libc::c_int, w, h);
+                    },
+                }
+            }
+        }
+
+        unsafe { (display.xlib.XFlush)(display.display) };
+        display.check_errors().expect("Failed to call XPutImage");
+    }
+
+    fn create_image(&mut self, width: u32, height: u32) -> Image {
+        if self.use_shm {
+            if let Some(image) = self.create_shm_image(width, height) {
+                return image;
+            }
+            self.use_shm = false;
+        }
+
+        let display = &self.display;
+        unsafe {
+            // the server converts the pixels if its byte order differs
+            let ximage = (display.xlib.XCreateImage)(display.display, self.visual, self.depth as libc::c_uint,
+                                                     ffi::ZPixmap, 0, ptr::null_mut(), width, height, 32, 0);
+            display.check_errors().expect("Failed to call XCreateImage");
+            assert!(!ximage.is_null(), "Failed to call XCreateImage");
+            (*ximage).byte_order = native_byte_order();
+
+            // freed by `XDestroyImage`
+            let size = (*ximage).bytes_per_line as usize * height as usize;
+            (*ximage).data = libc::calloc(size, 1) as *mut libc::c_char;
+
+            Image {
+                ximage: ximage,
+                width: width,
+                height: height,
+                shm: None,
+                busy: false,
+            }
+        }
+    }
+
+    fn create_shm_image(&self, width: u32, height: u32) -> Option<Image> {
+        let display = &self.display;
+        let xext = match display.xext {
+            Some(ref xext) => xext,
+            None => return None,
+        };
+
+        unsafe {
+            let mut info: Box<ffi::XShmSegmentInfo> = Box::new(mem::zeroed());
+            let ximage = (xext.XShmCreateImage)(display.display, self.visual, self.depth as libc::c_uint,
+                                                ffi::ZPixmap, ptr::null_mut(), &mut *info, width, height);
+            display.ignore_error();
+            if ximage.is_null() {
+                return None;
+            }
+
+            let size = (*ximage).bytes_per_line as usize * height as usize;
+            info.shmid = libc::shmget(libc::IPC_PRIVATE, size, libc::IPC_CREAT | 0o600);
+            if info.shmid < 0 {
+                (display.xlib.XDestroyImage)(ximage);
+                return None;
+            }
+            info.shmaddr = libc::shmat(info.shmid, ptr::null(), 0) as *mut libc::c_char;
+            // the segment is freed once both us and the server detached it
+            libc::shmctl(info.shmid, libc::IPC_RMID, ptr::null_mut());
+            if info.shmaddr as isize == -1 {
+                (display.xlib.XDestroyImage)(ximage);
+                return None;
+            }
+            (*ximage).data = info.shmaddr;
+            info.readOnly = ffi::False;
+
+            // attaching fails if the server is on another machine
+            (xext.XShmAttach)(display.display, &mut *info);
+            (display.xlib.XSync)(display.display, ffi::False);
+            if display.check_errors().is_err() {
+                (*ximage).data = ptr::null_mut();
+                (display.xlib.XDestroyImage)(ximage);
+                libc::shmdt(info.shmaddr as *const libc::c_void);
+                return None;
+            }
+
+            Some(Image {
+                ximage: ximage,
+                width: width,
+                height: height,
+                shm: Some(info),
+                busy: false,
+            })
+        }
+    }
+
+    fn destroy_image(&mut self) {
+        let display = &self.display;
+        if let Some(image) = self.image.take() {
+            unsafe {
+                match (image.shm, display.xext.as_ref()) {
+                    (Some(mut info), Some(xext)) => {
+                        (xext.XShmDetach)(display.display, &mut *info);
+                        (display.xlib.XSync)(display.display, ffi::False);
+                        // the data isn't ours to free
+                        (*image.ximage).data = ptr::null_mut();
+                        (display.xlib.XDestroyImage)(image.ximage);
+                        libc::shmdt(info.shmaddr as *const libc::c_void);
+                    },
+                    _ => {
+                        (display.xlib.XDestroyImage)(image.ximage);
+                    },
+                }
+            }
+            display.ignore_error();
+        }
+    }
+}
+
+impl Drop for SoftwareSurface {
+    fn drop(&mut self) {
+        self.destroy_image();
+        unsafe { (self.display.xlib.XFreeGC)(self.display.display, self.gc) };
+    }
+}
+
+// Restricts `rect` to the buffer, returning `None` if nothing is left.
+fn clip(rect: &Rect, width: u32, height: u32) -> Option<(u32, u32, u32, u32)> {
+    let x0 = cmp::max(rect.x, 0) as u32;
+    let y0 = cmp::max(rect.y, 0) as u32;
+    let x1 = cmp::min(rect.x as i64 + rect.width as i64, width as i64);
+    let y1 = cmp::min(rect.y as i64 + rect.height as i64, height as i64);
+    if x1 <= x0 as i64 || y1 <= y0 as i64 {
+        None
+    } else {
+        Some((x0, y0, x1 as u32 - x0, y1 as u32 - y0))
+    }
+}
+
+pub fn native_byte_order() -> libc::c_int {
+    if cfg!(target_endian = "little") { ffi::LSBFirst } else { ffi::MSBFirst }
+}
+
+// Converts a part of `pixels` to the format of the image.
+pub unsafe fn copy_pixels(xlib: &ffi::Xlib, ximage: *mut ffi::XImage, pixels: &[u8], width: u32, format: PixelFormat,
+                      x: u32, y: u32, w: u32, h: u32)
+{
+    let image = &*ximage;
+    // the remaining bits of a 32 bits visual hold the alpha
+    let alpha_mask = if image.depth == 32 {
+        !(image.red_mask | image.green_mask | image.blue_mask) & 0xffffffff
+    } else {
+        0
+    };
+    // places an 8 bits channel in the bits of `mask`
+    let pack = |channel: u8, mask: libc::c_ulong| -> libc::c_ulong {
+        if mask == 0 {
+            return 0;
+        }
+        let bits = cmp::min(mask.count_ones(), 8);
+        ((channel as libc::c_ulong) >> (8 - bits)) << mask.trailing_zeros()
+    };
+
+    for row in y .. y + h {
+        for column in x .. x + w {
+            let offset = (row as usize * width as usize + column as usize) * 4;
+            let pixel = &pixels[offset .. offset + 4];
+            let (r, g, b, a) = match format {
+                PixelFormat::Rgba8 => (pixel[0], pixel[1], pixel[2], pixel[3]),
+                PixelFormat::Bgra8 => (pixel[2], pixel[1], pixel[0], pixel[3]),
+            };
+
+            let value = pack(r, image.red_mask) | pack(g, image.green_mask) | pack(b, image.blue_mask) |
+                        pack(a, alpha_mask);
+
+            if image.bits_per_pixel == 32 {
+                let line = image.data.offset(row as isize * image.bytes_per_line as isize) as *mut u32;
+                *line.offset(column as isize) = value as u32;
+            } else {
+                // uncommon visuals go through the slow path
+                (xlib.XPutPixel)(ximage, column as libc::c_int, row as libc::c_int, value);
+            }
+        }
+    }
+}
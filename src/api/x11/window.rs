@@ -1,4 +1,4 @@
-use {Event, MouseCursor};
+use {Event, Icon, MouseCursor, CustomCursor, Selection, ClipboardData, ParentHandle, PixelFormat, RawHandle, Rect};
 use CreationError;
 use CreationError::OsError;
 use libc;
@@ -15,10 +15,18 @@ use std::time::Duration;
 use CursorState;
 use WindowAttributes;
 use platform::PlatformSpecificWindowBuilderAttributes;
+use os::unix::XWindowType;
 
 use platform::MonitorId as PlatformMonitorId;
 
+use super::cursor::CustomCursors;
 use super::input::XInputEventHandler;
+use super::selection::{self, Selections};
+use super::dnd::{self, Dnd};
+use super::frame_clock::FrameClock;
+use super::icon::{self, IconPixmaps};
+use super::identity;
+use super::software::SoftwareSurface;
 use super::{ffi};
 use super::{MonitorId, XConnection};
 
@@ -44,9 +52,9 @@ unsafe impl Send for WindowProxyData {}
 pub struct XWindow {
     display: Arc<XConnection>,
     window: ffi::Window,
-    is_fullscreen: bool,
     screen_id: libc::c_int,
-    xf86_desk_mode: Option<ffi::XF86VidModeModeInfo>,
+    // the video mode to switch back to, if the window switched it when created fullscreen
+    xf86_desk_mode: Mutex<Option<ffi::XF86VidModeModeInfo>>,
     ic: ffi::XIC,
     im: ffi::XIM,
     window_proxy_data: Arc<Mutex<Option<WindowProxyData>>>,
@@ -67,12 +75,7 @@ impl Drop for XWindow {
 
             let _lock = GLOBAL_XOPENIM_LOCK.lock().unwrap();
 
-            if self.is_fullscreen {
-                if let Some(mut xf86_desk_mode) = self.xf86_desk_mode {
-                    (self.display.xf86vmode.XF86VidModeSwitchToMode)(self.display.display, self.screen_id, &mut xf86_desk_mode);
-                }
-                (self.display.xf86vmode.XF86VidModeSetViewPort)(self.display.display, self.screen_id, 0, 0);
-            }
+            self.restore_desktop_mode();
 
             (self.display.xlib.XDestroyIC)(self.ic);
             (self.display.xlib.XCloseIM)(self.im);
@@ -81,6 +84,153 @@ impl Drop for XWindow {
     }
 }
 
+impl XWindow {
+    // Switches the screen back to the video mode that was active before the window was created,
+    // if the window switched it. Does nothing the next times.
+    fn restore_desktop_mode(&self) {
+        if let Some(mut xf86_desk_mode) = self.xf86_desk_mode.lock().unwrap().take() {
+            unsafe {
+                (self.display.xf86vmode.XF86VidModeSwitchToMode)(self.display.display, self.screen_id, &mut xf86_desk_mode);
+                (self.display.xf86vmode.XF86VidModeSetViewPort)(self.display.display, self.screen_id, 0, 0);
+            }
+        }
+    }
+}
+
+/// Constraints on the size of a window, sent to the window manager as `WM_NORMAL_HINTS`.
+#[derive(Clone, Default)]
+struct SizeConstraints {
+    min_dimensions: Option<(u32, u32)>,
+    max_dimensions: Option<(u32, u32)>,
+    /// Dimensions the window is locked to while it isn't resizable
+    fixed_dimensions: Option<(u32, u32)>,
+    aspect_ratio: Option<(u32, u32)>,
+    resize_increments: Option<(u32, u32)>,
+}
+
+impl SizeConstraints {
+    fn to_size_hints(&self) -> ffi::XSizeHints {
+        let mut size_hints: ffi::XSizeHints = unsafe { mem::zeroed() };
+
+        if let Some((width, height)) = self.fixed_dimensions.or(self.min_dimensions) {
+            size_hints.flags |= ffi::PMinSize;
+            size_hints.min_width = width as libc::c_int;
+            size_hints.min_height = height as libc::c_int;
+        }
+
+        if let Some((width, height)) = self.fixed_dimensions.or(self.max_dimensions) {
+            size_hints.flags |= ffi::PMaxSize;
+            size_hints.max_width = width as libc::c_int;
+            size_hints.max_height = height as libc::c_int;
+        }
+
+        if let Some((numerator, denominator)) = self.aspect_ratio {
+            let aspect = ffi::AspectRatio { x: numerator as libc::c_int, y: denominator as libc::c_int };
+            size_hints.flags |= ffi::PAspect;
+            size_hints.min_aspect = aspect;
+            size_hints.max_aspect = aspect;
+        }
+
+        if let Some((width_inc, height_inc)) = self.resize_increments {
+            size_hints.flags |= ffi::PResizeInc;
+            size_hints.width_inc = width_inc as libc::c_int;
+            size_hints.height_inc = height_inc as libc::c_int;
+        }
+
+        size_hints
+    }
+}
+
+/// Asks the window manager to show or hide the decorations of a window through the Motif
+/// hints, which are understood by nearly every window manager.
+fn set_motif_decorations(display: &XConnection, window: ffi::Window, decorations: bool) {
+    // flags, functions, decorations, input mode, status
+    const MWM_HINTS_DECORATIONS: libc::c_ulong = 1 << 1;
+    let hints: [libc::c_ulong; 5] = [MWM_HINTS_DECORATIONS, 0, decorations as libc::c_ulong, 0, 0];
+
+    let motif_wm_hints = display.get_atom("_MOTIF_WM_HINTS");
+    unsafe {
+        (display.xlib.XChangeProperty)(display.display, window, motif_wm_hints, motif_wm_hints,
+                                       32, ffi::PropModeReplace,
+                                       hints.as_ptr() as *const u8, hints.len() as libc::c_int);
+    }
+    display.check_errors().expect("Failed to set _MOTIF_WM_HINTS");
+}
+
+/// Returns the name of the `_NET_WM_WINDOW_TYPE_*` atom corresponding to `window_type`.
+fn window_type_atom_name(window_type: XWindowType) -> &'static str {
+    match window_type {
+        XWindowType::Normal => "_NET_WM_WINDOW_TYPE_NORMAL",
+        XWindowType::Utility => "_NET_WM_WINDOW_TYPE_UTILITY",
+        XWindowType::Dialog => "_NET_WM_WINDOW_TYPE_DIALOG",
+        XWindowType::Tooltip => "_NET_WM_WINDOW_TYPE_TOOLTIP",
+        XWindowType::PopupMenu => "_NET_WM_WINDOW_TYPE_POPUP_MENU",
+        XWindowType::Splash => "_NET_WM_WINDOW_TYPE_SPLASH",
+    }
+}
+
+/// Returns the XID of `parent` after checking that the window exists on `display`.
+fn check_parent(display: &XConnection, parent: ParentHandle) -> Result<ffi::Window, CreationError> {
+    let window = match parent {
+        ParentHandle::Xlib { window } => window,
+        ParentHandle::Xcb { window } => window as ffi::Window,
+        other => return Err(OsError(format!("Expected the handle of an X11 window as parent, got {:?}", other))),
+    };
+
+    let exists = unsafe {
+        let mut attributes: ffi::XWindowAttributes = mem::uninitialized();
+        let status = (display.xlib.XGetWindowAttributes)(display.display, window, &mut attributes);
+        display.check_errors().is_ok() && status != 0
+    };
+    if !exists {
+        return Err(OsError(format!("The parent window 0x{:x} doesn't exist on this X display", window)));
+    }
+
+    Ok(window)
+}
+
+/// Asks the window manager to add (or remove if `add` is false) up to two `_NET_WM_STATE`
+/// properties on a window. Pass `0` as `second` to change a single property.
+fn send_net_wm_state(display: &XConnection, window: ffi::Window, add: bool,
+                     first: ffi::Atom, second: ffi::Atom)
+{
+    let root = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
+    let state_atom = display.get_atom("_NET_WM_STATE");
+
+    let client_message_event = ffi::XClientMessageEvent {
+        type_: ffi::ClientMessage,
+        serial: 0,
+        send_event: 1,            // true because we are sending this through `XSendEvent`
+        display: display.display,
+        window: window,
+        message_type: state_atom, // the _NET_WM_STATE atom is sent to change the state of a window
+        format: 32,               // view `data` as `c_long`s
+        data: {
+            let mut data = ffi::ClientMessageData::new();
+            // This first `long` is the action; `1` means add/set following property, `0` remove.
+            data.set_long(0, if add { 1 } else { 0 });
+            // The second and third `long`s are the properties to change
+            data.set_long(1, first as c_long);
+            data.set_long(2, second as c_long);
+            // The source indication; `1` means a normal application
+            data.set_long(3, 1);
+            data
+        }
+    };
+    let mut x_event = ffi::XEvent::from(client_message_event);
+
+    unsafe {
+        (display.xlib.XSendEvent)(
+            display.display,
+            root,
+            0,
+            ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask,
+            &mut x_event as *mut _
+        );
+        display.check_errors().expect("Failed to call XSendEvent");
+    }
+}
+
 #[derive(Clone)]
 pub struct WindowProxy {
     data: Arc<Mutex<Option<WindowProxyData>>>,
@@ -156,18 +306,38 @@ impl<'a> Iterator for PollEventsIterator<'a> {
             if let Some(ev) = self.window.pending_events.lock().unwrap().pop_front() {
                 return Some(ev);
             }
+            if let Some(ev) = self.window.dnd.lock().unwrap().pop_event() {
+                return Some(ev);
+            }
 
             let mut xev = unsafe { mem::uninitialized() };
             let res = unsafe { (xlib.XCheckMaskEvent)(self.window.x.display.display, -1, &mut xev) };
 
             if res == 0 {
-                let res = unsafe { (xlib.XCheckTypedEvent)(self.window.x.display.display, ffi::ClientMessage, &mut xev) };
-
-                if res == 0 {
-                    let res = unsafe { (xlib.XCheckTypedEvent)(self.window.x.display.display, ffi::GenericEvent, &mut xev) };
-                    if res == 0 {
-                        return None;
+                // these events can't be selected with a mask, so they have to be retrieved by type
+                let unmaskable = [ffi::ClientMessage, ffi::SelectionRequest, ffi::SelectionClear,
+                                  ffi::GenericEvent];
+                let found = unmaskable.iter().any(|&ty| unsafe {
+                    (xlib.XCheckTypedEvent)(self.window.x.display.display, ty, &mut xev) != 0
+                }) || unsafe {
+                    // only the answers for the drags, the others are waited for where they are requested
+                    let xdnd_selection = self.window.x.display.get_atom("XdndSelection");
+                    (xlib.XCheckIfEvent)(self.window.x.display.display, &mut xev,
+                                         Some(dnd::is_xdnd_selection_notify),
+                                         xdnd_selection as *mut libc::c_char) != 0
+                };
+                if !found {
+                    self.window.dnd.lock().unwrap().check_timeout();
+                    if let Some(ev) = self.window.dnd.lock().unwrap().pop_event() {
+                        return Some(ev);
+                    }
+                    let visible = !self.window.is_minimized.get();
+                    if self.window.frame_clock.lock().unwrap()
+                                  .poll_timer(visible, &mut self.window.pending_events.lock().unwrap())
+                    {
+                        continue;
                     }
+                    return None;
                 }
             }
 
@@ -186,6 +356,12 @@ impl<'a> Iterator for PollEventsIterator<'a> {
                     if client_msg.data.get_long(0) == self.window.wm_delete_window as libc::c_long {
                         self.window.is_closed.store(true, Relaxed);
                         return Some(Closed);
+                    } else if dnd::find(&self.window.x.display, client_msg.window).map_or(false, |dnd| {
+                        // the message may be for another one of our windows
+                        dnd.lock().unwrap().handle_client_message(&self.window.x.display, client_msg.window,
+                                                                  client_msg)
+                    }) {
+                        continue;
                     } else {
                         return Some(Awakened);
                     }
@@ -203,7 +379,90 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
                 ffi::Expose => {
                     use events::Event::Refresh;
-                    return Some(Refresh);
+                    let xexpose: &ffi::XExposeEvent = unsafe { mem::transmute(&xev) };
+                    let mut exposed = self.window.exposed.lock().unwrap();
+                    exposed.push(Rect {
+                        x: xexpose.x,
+                        y: xexpose.y,
+                        width: xexpose.width as u32,
+                        height: xexpose.height as u32,
+                    });
+                    // `count` is the number of expose events that follow this one
+                    if xexpose.count == 0 {
+                        return Some(Refresh(mem::replace(&mut *exposed, Vec::new())));
+                    }
+                },
+
+                ffi::PropertyNotify => {
+                    let xprop: &ffi::XPropertyEvent = unsafe { mem::transmute(&xev) };
+                    if xprop.window == self.window.x.window && xprop.atom == self.window.net_wm_state {
+                        self.window.update_wm_state();
+                    } else {
+                        selection::handle_property_notify(&self.window.x.display, xprop);
+                    }
+                },
+
+                ffi::SelectionRequest => {
+                    let xreq: &ffi::XSelectionRequestEvent = unsafe { mem::transmute(&xev) };
+                    // the owner may be another one of our windows
+                    if let Some(selections) = selection::find_owner(&self.window.x.display, xreq.owner) {
+                        selections.lock().unwrap().handle_request(&self.window.x.display, xreq);
+                    }
+                },
+
+                ffi::SelectionClear => {
+                    let xclear: &ffi::XSelectionClearEvent = unsafe { mem::transmute(&xev) };
+                    if let Some(selections) = selection::find_owner(&self.window.x.display, xclear.window) {
+                        selections.lock().unwrap().clear(xclear.selection);
+                    }
+                },
+
+                // only selected on our windows and on the targets of our drops
+                ffi::DestroyNotify => {
+                    let xdestroy: &ffi::XDestroyWindowEvent = unsafe { mem::transmute(&xev) };
+                    dnd::handle_destroy_notify(&self.window.x.display, xdestroy.window);
+                },
+
+                ffi::SelectionNotify => {
+                    let xsel: &ffi::XSelectionEvent = unsafe { mem::transmute(&xev) };
+                    if let Some(dnd) = dnd::find(&self.window.x.display, xsel.requestor) {
+                        dnd.lock().unwrap().handle_selection_notify(&self.window.x.display, xsel.requestor, xsel);
+                    }
+                },
+
+                // core pointer events are only received while the pointer is grabbed for a drag
+                ffi::MotionNotify => {
+                    let xmotion: &ffi::XMotionEvent = unsafe { mem::transmute(&xev) };
+                    if let Some(dnd) = dnd::find(&self.window.x.display, xmotion.window) {
+                        dnd.lock().unwrap().handle_motion(&self.window.x.display, xmotion.window,
+                                                          xmotion.x_root, xmotion.y_root, xmotion.time);
+                    }
+                },
+
+                ffi::ButtonRelease => {
+                    let xbutton: &ffi::XButtonEvent = unsafe { mem::transmute(&xev) };
+                    if let Some(dnd) = dnd::find(&self.window.x.display, xbutton.window) {
+                        dnd.lock().unwrap().handle_button_release(&self.window.x.display, xbutton.window,
+                                                                  xbutton.time);
+                    }
+                },
+
+                ffi::UnmapNotify => {
+                    let xunmap: &ffi::XUnmapEvent = unsafe { mem::transmute(&xev) };
+                    // the window manager unmaps iconified windows, but so does `hide()`
+                    if xunmap.window == self.window.x.window && self.window.is_iconic() {
+                        self.window.update_minimized(true);
+                    }
+                },
+
+                ffi::MapNotify => {
+                    let xmap: &ffi::XMapEvent = unsafe { mem::transmute(&xev) };
+                    if xmap.window == self.window.x.window {
+                        self.window.update_minimized(false);
+                        if let Some(id) = self.window.startup_id.lock().unwrap().take() {
+                            identity::complete_startup(&self.window.x.display, self.window.x.window, &id);
+                        }
+                    }
                 },
 
                 ffi::KeyPress | ffi::KeyRelease => {
@@ -216,12 +475,30 @@ impl<'a> Iterator for PollEventsIterator<'a> {
 
                 ffi::GenericEvent => {
                     if let Some(cookie) = GenericEventCookie::from_event(self.window.x.display.borrow(), xev) {
+                        // the event types of the extensions overlap
+                        if self.window.frame_clock.lock().unwrap().handle_generic_event(
+                            &cookie.cookie, self.window.x.window, &mut self.window.pending_events.lock().unwrap())
+                        {
+                            continue;
+                        }
+
                         match cookie.cookie.evtype {
-                            ffi::XI_DeviceChanged...ffi::XI_LASTEVENT => {
+                            ffi::XI_DeviceChanged..=ffi::XI_LASTEVENT => {
                                 match self.window.input_handler.lock() {
                                     Ok(mut handler) => {
                                         match handler.translate_event(&cookie.cookie) {
-                                            Some(event) => self.window.pending_events.lock().unwrap().push_back(event),
+                                            Some(event) => {
+                                                // the urgency hint is left to the application to clear,
+                                                // unless it was set by `request_user_attention`
+                                                if let Event::Focused(true) = event {
+                                                    use std::sync::atomic::Ordering::Relaxed;
+                                                    if self.window.demands_attention.swap(false, Relaxed) {
+                                                        identity::set_urgency(&self.window.x.display,
+                                                                              self.window.x.window, false);
+                                                    }
+                                                }
+                                                self.window.pending_events.lock().unwrap().push_back(event)
+                                            },
                                             None => {}
                                         }
                                     },
@@ -254,12 +531,27 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
             if let Some(ev) = self.window.pending_events.lock().unwrap().pop_front() {
                 return Some(ev);
             }
+            if let Some(ev) = self.window.dnd.lock().unwrap().pop_event() {
+                return Some(ev);
+            }
 
-            // this will block until an event arrives, but doesn't remove
-            // it from the queue
-            let mut xev = unsafe { mem::uninitialized() };
-            unsafe { (self.window.x.display.xlib.XPeekEvent)(self.window.x.display.display, &mut xev) };
-            self.window.x.display.check_errors().expect("Failed to call XPeekEvent");
+            let visible = !self.window.is_minimized.get();
+            let redraw_timeout = self.window.frame_clock.lock().unwrap().timeout(visible);
+            let drop_timeout = self.window.dnd.lock().unwrap().timeout();
+            let timeout = match (redraw_timeout, drop_timeout) {
+                (Some(a), Some(b)) => Some(cmp::min(a, b)),
+                (a, b) => a.or(b)
+            };
+            if let Some(timeout) = timeout {
+                // a redraw is due or a drop times out once the timeout elapses
+                self.window.wait_for_event(timeout);
+            } else {
+                // this will block until an event arrives, but doesn't remove
+                // it from the queue
+                let mut xev = unsafe { mem::uninitialized() };
+                unsafe { (self.window.x.display.xlib.XPeekEvent)(self.window.x.display.display, &mut xev) };
+                self.window.x.display.check_errors().expect("Failed to call XPeekEvent");
+            }
 
             // calling poll_events()
             if let Some(ev) = self.window.poll_events().next() {
@@ -274,11 +566,39 @@ impl<'a> Iterator for WaitEventsIterator<'a> {
 pub struct Window {
     pub x: Arc<XWindow>,
     is_closed: AtomicBool,
+    /// Whether `request_user_attention` set the urgency hint, which the focus clears
+    demands_attention: AtomicBool,
     wm_delete_window: ffi::Atom,
+    net_wm_state: ffi::Atom,
+    net_wm_state_fullscreen: ffi::Atom,
+    net_wm_state_maximized_vert: ffi::Atom,
+    net_wm_state_maximized_horz: ffi::Atom,
+    net_wm_state_hidden: ffi::Atom,
+    icccm_wm_state: ffi::Atom,
+    /// Last-seen value of the `_NET_WM_STATE` property, as set by the window manager
+    wm_state: Mutex<Vec<ffi::Atom>>,
+    /// Whether the window was iconified the last time we checked
+    is_minimized: Cell<bool>,
+    size_constraints: Mutex<SizeConstraints>,
+    /// Shared with the other windows, which may read the requests for these selections
+    selections: Arc<Mutex<Selections>>,
+    /// Shared with the other windows, which may read the messages of the drags
+    dnd: Arc<Mutex<Dnd>>,
+    /// Created the first time pixels are presented
+    software: Mutex<Option<SoftwareSurface>>,
+    frame_clock: Mutex<FrameClock>,
+    /// Regions exposed by the `Expose` events received so far, until the last one of the series
+    exposed: Mutex<Vec<Rect>>,
     current_size: Cell<(libc::c_int, libc::c_int)>,
     /// Events that have been retreived with XLib but not dispatched with iterators yet
     pending_events: Mutex<VecDeque<Event>>,
     cursor_state: Mutex<CursorState>,
+    /// Created from the images given to `set_custom_cursor`
+    custom_cursors: Mutex<CustomCursors>,
+    /// The legacy icon of the window, if it has one
+    icon_pixmaps: Mutex<Option<IconPixmaps>>,
+    /// The startup notification to complete once the window gets mapped
+    startup_id: Mutex<Option<String>>,
     input_handler: Mutex<XInputEventHandler>
 }
 
@@ -305,6 +625,18 @@ impl Window {
 
         };
 
+        let parent = match window_attrs.parent {
+            Some(parent) => Some(try!(check_parent(display, parent))),
+            None => None,
+        };
+
+        // the names are given to Xlib as C strings
+        let (instance, class) = pl_attribs.class.clone().unwrap_or_else(identity::default_class);
+        let (instance, class) = match (::std::ffi::CString::new(instance), ::std::ffi::CString::new(class)) {
+            (Ok(instance), Ok(class)) => (instance, class),
+            _ => return Err(OsError("The names of WM_CLASS can't contain NUL bytes".to_string())),
+        };
+
         let screen_id = match pl_attribs.screen_id {
             Some(id) => id,
             None => match window_attrs.monitor {
@@ -349,6 +681,9 @@ impl Window {
         let root = unsafe { (display.xlib.XDefaultRootWindow)(display.display) };
         display.check_errors().expect("Failed to get root window");
 
+        // an embedded window is created inside of its parent instead of the root window
+        let parent_window = parent.unwrap_or(root);
+
         // creating
         let mut set_win_attr = {
             let mut swa: ffi::XSetWindowAttributes = unsafe { mem::zeroed() };
@@ -361,7 +696,7 @@ impl Window {
             swa.event_mask = ffi::ExposureMask | ffi::StructureNotifyMask |
                 ffi::VisibilityChangeMask | ffi::KeyPressMask | ffi::PointerMotionMask |
                 ffi::KeyReleaseMask | ffi::ButtonPressMask |
-                ffi::ButtonReleaseMask | ffi::KeymapStateMask;
+                ffi::ButtonReleaseMask | ffi::KeymapStateMask | ffi::PropertyChangeMask;
             swa.border_pixel = 0;
             if window_attrs.transparent {
                 swa.background_pixel = 0;
@@ -378,7 +713,7 @@ impl Window {
 
         // finally creating the window
         let window = unsafe {
-            let win = (display.xlib.XCreateWindow)(display.display, root, 0, 0, dimensions.0 as libc::c_uint,
+            let win = (display.xlib.XCreateWindow)(display.display, parent_window, 0, 0, dimensions.0 as libc::c_uint,
                 dimensions.1 as libc::c_uint, 0,
                 match pl_attribs.visual_infos {
                     Some(vi) => vi.depth,
@@ -395,6 +730,61 @@ impl Window {
             win
         };
 
+        // set the hints that the window manager reads when the window gets mapped
+        identity::set_class(display, window, &instance, &class);
+        identity::set_client_properties(display, window);
+        // the launcher waits for a top-level window of the application, which only the
+        // application knows, since a plugin shares the environment of its host
+        let startup_id = if parent.is_none() && pl_attribs.startup_notification {
+            identity::take_startup_id()
+        } else {
+            None
+        };
+        if let Some(ref id) = startup_id {
+            identity::set_startup_id(display, window, id);
+        }
+        let icon_pixmaps = if window_attrs.window_icon.is_empty() {
+            None
+        } else {
+            icon::set_icon(display, window, screen_id, &window_attrs.window_icon)
+        };
+        unsafe {
+            let window_type = display.get_atom(window_type_atom_name(pl_attribs.window_type));
+            (display.xlib.XChangeProperty)(display.display, window,
+                                           display.get_atom("_NET_WM_WINDOW_TYPE"), ffi::XA_ATOM,
+                                           32, ffi::PropModeReplace,
+                                           &window_type as *const ffi::Atom as *const u8, 1);
+            display.check_errors().expect("Failed to set _NET_WM_WINDOW_TYPE");
+
+            if let Some(transient_for) = pl_attribs.transient_for {
+                (display.xlib.XSetTransientForHint)(display.display, window, transient_for);
+                display.check_errors().expect("Failed to call XSetTransientForHint");
+            }
+
+            // before mapping, the initial `_NET_WM_STATE` is set directly on the window
+            let initial_state: Vec<ffi::Atom> = [
+                (pl_attribs.modal, "_NET_WM_STATE_MODAL"),
+                (pl_attribs.skip_taskbar, "_NET_WM_STATE_SKIP_TASKBAR"),
+                (pl_attribs.skip_pager, "_NET_WM_STATE_SKIP_PAGER"),
+                (pl_attribs.always_on_top, "_NET_WM_STATE_ABOVE"),
+            ].iter().filter(|&&(enabled, _)| enabled).map(|&(_, name)| display.get_atom(name)).collect();
+
+            if !window_attrs.decorations {
+                set_motif_decorations(display, window, false);
+            }
+
+            dnd::set_aware(display, window);
+
+            if !initial_state.is_empty() {
+                (display.xlib.XChangeProperty)(display.display, window,
+                                               display.get_atom("_NET_WM_STATE"), ffi::XA_ATOM,
+                                               32, ffi::PropModeReplace,
+                                               initial_state.as_ptr() as *const u8,
+                                               initial_state.len() as libc::c_int);
+                display.check_errors().expect("Failed to set _NET_WM_STATE");
+            }
+        }
+
         // set visibility
         if window_attrs.visible {
             unsafe {
@@ -458,63 +848,23 @@ impl Window {
             }
         }
 
-        // Set ICCCM WM_CLASS property based on initial window title
-        unsafe {
-            with_c_str(&*window_attrs.title, |c_name| {
-                let hint = (display.xlib.XAllocClassHint)();
-                (*hint).res_name = c_name as *mut libc::c_char;
-                (*hint).res_class = c_name as *mut libc::c_char;
-                (display.xlib.XSetClassHint)(display.display, window, hint);
-                display.check_errors().expect("Failed to call XSetClassHint");
-                (display.xlib.XFree)(hint as *mut _);
-            });
-        }
+        let net_wm_state = display.get_atom("_NET_WM_STATE");
+        let net_wm_state_fullscreen = display.get_atom("_NET_WM_STATE_FULLSCREEN");
+        let net_wm_state_maximized_vert = display.get_atom("_NET_WM_STATE_MAXIMIZED_VERT");
+        let net_wm_state_maximized_horz = display.get_atom("_NET_WM_STATE_MAXIMIZED_HORZ");
+        let net_wm_state_hidden = display.get_atom("_NET_WM_STATE_HIDDEN");
+        let icccm_wm_state = display.get_atom("WM_STATE");
 
         let is_fullscreen = window_attrs.monitor.is_some();
 
-        if is_fullscreen {
-            let state_atom = unsafe {
-                with_c_str("_NET_WM_STATE", |state|
-                    (display.xlib.XInternAtom)(display.display, state, 0)
-                )
-            };
-            display.check_errors().expect("Failed to call XInternAtom");
-            let fullscreen_atom = unsafe {
-                with_c_str("_NET_WM_STATE_FULLSCREEN", |state_fullscreen|
-                    (display.xlib.XInternAtom)(display.display, state_fullscreen, 0)
-                )
-            };
-            display.check_errors().expect("Failed to call XInternAtom");
-
-            let client_message_event = ffi::XClientMessageEvent {
-                type_: ffi::ClientMessage,
-                serial: 0,
-                send_event: 1,            // true because we are sending this through `XSendEvent`
-                display: display.display,
-                window: window,
-                message_type: state_atom, // the _NET_WM_STATE atom is sent to change the state of a window
-                format: 32,               // view `data` as `c_long`s
-                data: {
-                    let mut data = ffi::ClientMessageData::new();
-                    // This first `long` is the action; `1` means add/set following property.
-                    data.set_long(0, 1);
-                    // This second `long` is the property to set (fullscreen)
-                    data.set_long(1, fullscreen_atom as c_long);
-                    data
-                }
-            };
-            let mut x_event = ffi::XEvent::from(client_message_event);
+        let size_constraints = SizeConstraints {
+            min_dimensions: window_attrs.min_dimensions,
+            max_dimensions: window_attrs.max_dimensions,
+            .. SizeConstraints::default()
+        };
 
-            unsafe {
-                (display.xlib.XSendEvent)(
-                    display.display,
-                    root,
-                    0,
-                    ffi::SubstructureRedirectMask | ffi::SubstructureNotifyMask,
-                    &mut x_event as *mut _
-                );
-                display.check_errors().expect("Failed to call XSendEvent");
-            }
+        if is_fullscreen {
+            send_net_wm_state(display, window, true, net_wm_state_fullscreen, 0);
 
             if let Some(mut mode_to_switch_to) = mode_to_switch_to {
                 unsafe {
@@ -537,23 +887,11 @@ impl Window {
         } else {
 
             // set size hints
-            let mut size_hints: ffi::XSizeHints = unsafe { mem::zeroed() };
-            size_hints.flags = ffi::PSize;
+            let mut size_hints = size_constraints.to_size_hints();
+            size_hints.flags |= ffi::PSize;
             size_hints.width = dimensions.0 as i32;
             size_hints.height = dimensions.1 as i32;
 
-            if let Some(dimensions) = window_attrs.min_dimensions {
-                size_hints.flags |= ffi::PMinSize;
-                size_hints.min_width = dimensions.0 as i32;
-                size_hints.min_height = dimensions.1 as i32;
-            }
-
-            if let Some(dimensions) = window_attrs.max_dimensions {
-                size_hints.flags |= ffi::PMaxSize;
-                size_hints.max_width = dimensions.0 as i32;
-                size_hints.max_height = dimensions.1 as i32;
-            }
-
             unsafe {
                 (display.xlib.XSetNormalHints)(display.display, window, &mut size_hints);
                 display.check_errors().expect("Failed to call XSetNormalHints");
@@ -568,6 +906,8 @@ impl Window {
         };
         let window_proxy_data = Arc::new(Mutex::new(Some(window_proxy_data)));
 
+        let frame_clock = FrameClock::new(display, window);
+
         let window = Window {
             x: Arc::new(XWindow {
                 display: display.clone(),
@@ -575,21 +915,44 @@ impl Window {
                 im: im,
                 ic: ic,
                 screen_id: screen_id,
-                is_fullscreen: is_fullscreen,
-                xf86_desk_mode: xf86_desk_mode,
+                xf86_desk_mode: Mutex::new(if is_fullscreen && mode_to_switch_to.is_some() {
+                    xf86_desk_mode
+                } else {
+                    None
+                }),
                 window_proxy_data: window_proxy_data,
             }),
             is_closed: AtomicBool::new(false),
+            demands_attention: AtomicBool::new(false),
             wm_delete_window: wm_delete_window,
+            net_wm_state: net_wm_state,
+            net_wm_state_fullscreen: net_wm_state_fullscreen,
+            net_wm_state_maximized_vert: net_wm_state_maximized_vert,
+            net_wm_state_maximized_horz: net_wm_state_maximized_horz,
+            net_wm_state_hidden: net_wm_state_hidden,
+            icccm_wm_state: icccm_wm_state,
+            wm_state: Mutex::new(Vec::new()),
+            is_minimized: Cell::new(false),
+            size_constraints: Mutex::new(size_constraints),
+            selections: Selections::register(display, window),
+            dnd: Dnd::register(display, window),
+            software: Mutex::new(None),
+            frame_clock: Mutex::new(frame_clock),
+            exposed: Mutex::new(Vec::new()),
             current_size: Cell::new((0, 0)),
             pending_events: Mutex::new(VecDeque::new()),
             cursor_state: Mutex::new(CursorState::Normal),
+            custom_cursors: Mutex::new(CustomCursors::new(display)),
+            icon_pixmaps: Mutex::new(icon_pixmaps),
+            startup_id: Mutex::new(startup_id),
             input_handler: Mutex::new(XInputEventHandler::new(display, window, ic, window_attrs))
         };
 
         window.set_title(&window_attrs.title);
 
-        if window_attrs.visible {
+        // the host manages the focus of an embedded window, which only becomes viewable once the
+        // parent is
+        if window_attrs.visible && parent.is_none() {
             unsafe {
                 let ref x_window: &XWindow = window.x.borrow();
 
@@ -645,6 +1008,169 @@ impl Window {
 
     }
 
+    pub fn set_window_icon(&self, icon: &[Icon]) -> Result<(), CreationError> {
+        // the previous pixmaps are freed once the hints refer to the new ones
+        *self.icon_pixmaps.lock().unwrap() = icon::set_icon(&self.x.display, self.x.window,
+                                                            self.x.screen_id, icon);
+        unsafe { (self.x.display.xlib.XFlush)(self.x.display.display) };
+        Ok(())
+    }
+
+    pub fn request_user_attention(&self) {
+        use std::sync::atomic::Ordering::Relaxed;
+        self.demands_attention.store(true, Relaxed);
+        identity::set_urgency(&self.x.display, self.x.window, true);
+        send_net_wm_state(&self.x.display, self.x.window, true,
+                          self.x.display.get_atom("_NET_WM_STATE_DEMANDS_ATTENTION"), 0);
+        unsafe { (self.x.display.xlib.XFlush)(self.x.display.display) };
+    }
+
+    // The video mode is only switched when the window is created fullscreen. At runtime the
+    // window manager resizes the window to the monitor, and leaving fullscreen only switches back
+    // a mode switched at creation.
+    pub fn set_fullscreen(&self, monitor: Option<PlatformMonitorId>) {
+        match monitor {
+            Some(monitor) => {
+                if let PlatformMonitorId::X(MonitorId(_, screen)) = monitor {
+                    if screen as libc::c_int != self.x.screen_id {
+                        warn!("[winit] An existing window can't be moved to another X screen, \
+                               going fullscreen on its current screen instead");
+                    }
+                }
+                send_net_wm_state(&self.x.display, self.x.window, true, self.net_wm_state_fullscreen, 0);
+            },
+            None => {
+                self.x.restore_desktop_mode();
+                send_net_wm_state(&self.x.display, self.x.window, false, self.net_wm_state_fullscreen, 0);
+            },
+        }
+
+        unsafe { (self.x.display.xlib.XFlush)(self.x.display.display) };
+        self.x.display.check_errors().expect("Failed to set fullscreen state");
+    }
+
+    pub fn set_decorations(&self, decorations: bool) {
+        set_motif_decorations(&self.x.display, self.x.window, decorations);
+        unsafe { (self.x.display.xlib.XFlush)(self.x.display.display) };
+        self.x.display.check_errors().expect("Failed to call XFlush");
+    }
+
+    pub fn set_maximized(&self, maximized: bool) {
+        send_net_wm_state(&self.x.display, self.x.window, maximized,
+                          self.net_wm_state_maximized_vert, self.net_wm_state_maximized_horz);
+        unsafe { (self.x.display.xlib.XFlush)(self.x.display.display) };
+        self.x.display.check_errors().expect("Failed to set maximized state");
+    }
+
+    pub fn set_minimized(&self, minimized: bool) {
+        unsafe {
+            if minimized {
+                (self.x.display.xlib.XIconifyWindow)(self.x.display.display, self.x.window,
+                                                     self.x.screen_id);
+            } else {
+                // mapping an iconic window brings it back to the normal state
+                (self.x.display.xlib.XMapRaised)(self.x.display.display, self.x.window);
+            }
+            (self.x.display.xlib.XFlush)(self.x.display.display);
+        }
+        self.x.display.check_errors().expect("Failed to set minimized state");
+    }
+
+    pub fn is_maximized(&self) -> bool {
+        let state = self.get_wm_state();
+        state.contains(&self.net_wm_state_maximized_vert) &&
+            state.contains(&self.net_wm_state_maximized_horz)
+    }
+
+    pub fn is_minimized(&self) -> bool {
+        self.get_wm_state().contains(&self.net_wm_state_hidden) || self.is_iconic()
+    }
+
+    /// Reads a property of the window made of 32 bits items, and returns them as `c_ulong`s.
+    ///
+    /// Returns an empty list if the property doesn't exist or doesn't have the expected type.
+    fn get_property(&self, property: ffi::Atom, property_type: ffi::Atom) -> Vec<libc::c_ulong> {
+        unsafe {
+            let mut actual_type = 0;
+            let mut actual_format = 0;
+            let mut nitems = 0;
+            let mut bytes_after = 0;
+            let mut data: *mut libc::c_uchar = ptr::null_mut();
+
+            let result = (self.x.display.xlib.XGetWindowProperty)(
+                self.x.display.display, self.x.window, property,
+                0, 1024, ffi::False, property_type,
+                &mut actual_type, &mut actual_format, &mut nitems, &mut bytes_after, &mut data
+            );
+            self.x.display.check_errors().expect("Failed to call XGetWindowProperty");
+
+            let mut items = Vec::new();
+            if result == ffi::Success as libc::c_int && !data.is_null() {
+                if actual_type == property_type && actual_format == 32 {
+                    // format 32 properties are returned as an array of `c_long`s
+                    let data = data as *const libc::c_ulong;
+                    items.extend((0 .. nitems as isize).map(|i| *data.offset(i)));
+                }
+                (self.x.display.xlib.XFree)(data as *mut _);
+            }
+            items
+        }
+    }
+
+    /// Reads the list of states that the window manager currently applies to the window.
+    fn get_wm_state(&self) -> Vec<ffi::Atom> {
+        self.get_property(self.net_wm_state, ffi::XA_ATOM).into_iter()
+            .map(|atom| atom as ffi::Atom).collect()
+    }
+
+    /// Returns true if the ICCCM `WM_STATE` of the window is `IconicState`.
+    fn is_iconic(&self) -> bool {
+        const ICONIC_STATE: libc::c_ulong = 3;
+        self.get_property(self.icccm_wm_state, self.icccm_wm_state).first() == Some(&ICONIC_STATE)
+    }
+
+    // Compares the new value of `_NET_WM_STATE` to the previous one, and queues an event for
+    // each state that has changed.
+    fn update_wm_state(&self) {
+        use events::Event::{Fullscreen, Maximized};
+
+        let new_state = self.get_wm_state();
+        let mut wm_state = self.wm_state.lock().unwrap();
+
+        let was_fullscreen = wm_state.contains(&self.net_wm_state_fullscreen);
+        let is_fullscreen = new_state.contains(&self.net_wm_state_fullscreen);
+        if was_fullscreen != is_fullscreen {
+            self.pending_events.lock().unwrap().push_back(Fullscreen(is_fullscreen));
+        }
+
+        let was_maximized = wm_state.contains(&self.net_wm_state_maximized_vert) &&
+                            wm_state.contains(&self.net_wm_state_maximized_horz);
+        let is_maximized = new_state.contains(&self.net_wm_state_maximized_vert) &&
+                           new_state.contains(&self.net_wm_state_maximized_horz);
+        if was_maximized != is_maximized {
+            self.pending_events.lock().unwrap().push_back(Maximized(is_maximized));
+        }
+
+        // not all window managers set `_NET_WM_STATE_HIDDEN`, `UnmapNotify` covers the others
+        if new_state.contains(&self.net_wm_state_hidden) {
+            self.update_minimized(true);
+        } else if wm_state.contains(&self.net_wm_state_hidden) {
+            self.update_minimized(false);
+        }
+
+        *wm_state = new_state;
+    }
+
+    // Queues a `Minimized` event if the state differs from the last one reported.
+    fn update_minimized(&self, minimized: bool) {
+        use events::Event::Minimized;
+
+        if self.is_minimized.get() != minimized {
+            self.is_minimized.set(minimized);
+            self.pending_events.lock().unwrap().push_back(Minimized(minimized));
+        }
+    }
+
     pub fn show(&self) {
         unsafe {
             (self.x.display.xlib.XMapRaised)(self.x.display.display, self.x.window);
@@ -710,6 +1236,127 @@ impl Window {
         self.x.display.check_errors().expect("Failed to call XResizeWindow");
     }
 
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.size_constraints.lock().unwrap().min_dimensions = dimensions;
+        self.update_size_hints();
+    }
+
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.size_constraints.lock().unwrap().max_dimensions = dimensions;
+        self.update_size_hints();
+    }
+
+    pub fn set_resizable(&self, resizable: bool) {
+        // a window that can't be resized has its minimum and maximum sizes set to its current size
+        let fixed_dimensions = if resizable { None } else { self.get_inner_size() };
+        self.size_constraints.lock().unwrap().fixed_dimensions = fixed_dimensions;
+        self.update_size_hints();
+    }
+
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        self.size_constraints.lock().unwrap().aspect_ratio = ratio;
+        self.update_size_hints();
+    }
+
+    pub fn set_resize_increments(&self, increments: Option<(u32, u32)>) {
+        self.size_constraints.lock().unwrap().resize_increments = increments;
+        self.update_size_hints();
+    }
+
+    fn update_size_hints(&self) {
+        let mut size_hints = self.size_constraints.lock().unwrap().to_size_hints();
+        unsafe {
+            (self.x.display.xlib.XSetNormalHints)(self.x.display.display, self.x.window, &mut size_hints);
+            (self.x.display.xlib.XFlush)(self.x.display.display);
+        }
+        self.x.display.check_errors().expect("Failed to call XSetNormalHints");
+    }
+
+    pub fn set_clipboard(&self, selection: Selection, contents: Vec<ClipboardData>) {
+        let selection = selection::selection_atom(&self.x.display, selection);
+        self.set_clipboard_atom(selection, contents);
+    }
+
+    fn set_clipboard_atom(&self, selection: ffi::Atom, contents: Vec<ClipboardData>) {
+        self.selections.lock().unwrap().set(selection, contents);
+        unsafe {
+            (self.x.display.xlib.XSetSelectionOwner)(self.x.display.display, selection, self.x.window,
+                                                     ffi::CurrentTime);
+            (self.x.display.xlib.XFlush)(self.x.display.display);
+        }
+        self.x.display.check_errors().expect("Failed to call XSetSelectionOwner");
+    }
+
+    pub fn get_clipboard(&self, selection: Selection, mime_type: &str) -> Option<Vec<u8>> {
+        let selection = selection::selection_atom(&self.x.display, selection);
+
+        // the event loops aren't running while we wait, so our windows can't answer our request
+        if let Some(owner) = selection::find_selection_owner(&self.x.display, selection) {
+            return owner.lock().unwrap().get(selection).and_then(|contents| {
+                contents.iter().find(|c| c.mime_type == mime_type).map(|c| c.data.clone())
+            });
+        }
+
+        let target = self.x.display.get_atom(mime_type);
+        selection::convert_selection(&self.x.display, self.x.window, selection, target, ffi::CurrentTime)
+            .map(|(_, data)| data)
+    }
+
+    pub fn get_clipboard_mime_types(&self, selection: Selection) -> Vec<String> {
+        let selection = selection::selection_atom(&self.x.display, selection);
+
+        if let Some(owner) = selection::find_selection_owner(&self.x.display, selection) {
+            return owner.lock().unwrap().get(selection).map(|contents| {
+                contents.iter().map(|c| c.mime_type.clone()).collect()
+            }).unwrap_or(Vec::new());
+        }
+
+        selection::get_targets(&self.x.display, self.x.window, selection)
+    }
+
+    pub fn request_redraw(&self) {
+        self.frame_clock.lock().unwrap().request_redraw(&self.x.display, self.x.window);
+    }
+
+    // Blocks until an event is available or the timeout elapses.
+    fn wait_for_event(&self, timeout: Duration) {
+        let display = &self.x.display;
+        unsafe {
+            if (display.xlib.XPending)(display.display) != 0 {
+                return;
+            }
+            let mut fd = libc::pollfd {
+                fd: (display.xlib.XConnectionNumber)(display.display),
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout = timeout.as_secs() * 1000 + timeout.subsec_nanos() as u64 / 1_000_000;
+            libc::poll(&mut fd, 1, cmp::max(timeout, 1) as libc::c_int);
+        }
+    }
+
+    pub fn present_pixels(&self, pixels: &[u8], width: u32, height: u32, format: PixelFormat,
+                          damage: Option<&[Rect]>)
+    {
+        let mut software = self.software.lock().unwrap();
+        if software.is_none() {
+            *software = Some(SoftwareSurface::new(&self.x.display, self.x.window));
+        }
+        software.as_mut().unwrap().present(self.x.window, pixels, width, height, format, damage);
+    }
+
+    pub fn start_drag(&self, contents: Vec<ClipboardData>) {
+        let mut dnd = self.dnd.lock().unwrap();
+        if dnd.is_dragging() {
+            return;
+        }
+
+        // the target reads the data through the `XdndSelection` selection
+        let types = contents.iter().map(|c| self.x.display.get_atom(&c.mime_type)).collect();
+        self.set_clipboard_atom(self.x.display.get_atom("XdndSelection"), contents);
+        dnd.start_drag(&self.x.display, self.x.window, types);
+    }
+
     #[inline]
     pub fn create_window_proxy(&self) -> WindowProxy {
         WindowProxy {
@@ -751,6 +1398,15 @@ impl Window {
         self.x.display.display as *mut libc::c_void
     }
 
+    #[inline]
+    pub fn raw_handle(&self) -> RawHandle {
+        RawHandle::Xlib {
+            window: self.x.window,
+            display: self.x.display.display as *mut libc::c_void,
+            screen: self.x.screen_id,
+        }
+    }
+
     #[inline]
     pub fn get_xlib_window(&self) -> *mut libc::c_void {
         self.x.window as *mut libc::c_void
@@ -840,6 +1496,15 @@ impl Window {
         }
     }
 
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        // the cursor stays in the cache, to be defined again
+        let xcursor = self.custom_cursors.lock().unwrap().get(cursor);
+        unsafe {
+            (self.x.display.xlib.XDefineCursor)(self.x.display.display, self.x.window, xcursor);
+            self.x.display.check_errors().expect("Failed to set the cursor");
+        }
+    }
+
     fn load_cursor(&self, name: &str) -> ffi::Cursor {
         use std::ffi::CString;
         unsafe {
@@ -886,7 +1551,11 @@ impl Window {
     }
 
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        use CursorState::{ Grab, Normal, Hide };
+        use CursorState::{ Grab, Normal, Hide, Lock };
+
+        if let Lock = state {
+            return Err("Cursor cannot be locked on X11 yet.".to_string());
+        }
 
         let mut cursor_state = self.cursor_state.lock().unwrap();
         match (state, *cursor_state) {
@@ -901,7 +1570,7 @@ impl Window {
                     self.x.display.check_errors().expect("Failed to call XUngrabPointer");
                 }
             },
-            Normal => {},
+            Normal | Lock => {},
             Hide => {
                 // NB: Calling XDefineCursor with None (aka 0)
                 // as a value resets the cursor to the default.
@@ -913,7 +1582,8 @@ impl Window {
 
         *cursor_state = state;
         match state {
-            Normal => Ok(()),
+            // locking was refused before releasing the cursor
+            Normal | Lock => Ok(()),
             Hide => {
                 unsafe {
                     let cursor = self.create_empty_cursor();
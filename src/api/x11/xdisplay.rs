@@ -16,6 +16,10 @@ pub struct XConnection {
     pub xcursor: ffi::Xcursor,
     pub xinput2: ffi::XInput2,
     pub xlib_xcb: ffi::Xlib_xcb,
+    /// MIT-SHM, which isn't required.
+    pub xext: Option<ffi::Xext>,
+    /// The Present extension, which isn't required.
+    pub xpresent: Option<ffi::Xpresent>,
     pub display: *mut ffi::Display,
     pub latest_error: Mutex<Option<XError>>,
 }
@@ -33,6 +37,8 @@ impl XConnection {
         let xf86vmode = try!(ffi::Xf86vmode::open());
         let xinput2 = try!(ffi::XInput2::open());
         let xlib_xcb = try!(ffi::Xlib_xcb::open());
+        let xext = ffi::Xext::open().ok();
+        let xpresent = ffi::Xpresent::open().ok();
 
         unsafe { (xlib.XInitThreads)() };
         unsafe { (xlib.XSetErrorHandler)(error_handler) };
@@ -52,6 +58,8 @@ impl XConnection {
             xcursor: xcursor,
             xinput2: xinput2,
             xlib_xcb: xlib_xcb,
+            xext: xext,
+            xpresent: xpresent,
             display: display,
             latest_error: Mutex::new(None),
         })
@@ -74,6 +82,14 @@ impl XConnection {
     pub fn ignore_error(&self) {
         *self.latest_error.lock().unwrap() = None;
     }
+
+    /// Returns the atom corresponding to `name`, creating it if it doesn't exist yet.
+    pub fn get_atom(&self, name: &str) -> ffi::Atom {
+        let c_name = CString::new(name).unwrap();
+        let atom = unsafe { (self.xlib.XInternAtom)(self.display, c_name.as_ptr(), ffi::False) };
+        self.check_errors().expect("Failed to call XInternAtom");
+        atom
+    }
 }
 
 impl Drop for XConnection {
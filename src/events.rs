@@ -1,5 +1,7 @@
 use std::path::PathBuf;
 
+use {DragAction, Rect};
+
 #[derive(Clone, Debug)]
 pub enum Event {
     /// The size of the window has changed.
@@ -14,6 +16,23 @@ pub enum Event {
     /// A file has been dropped into the window.
     DroppedFile(PathBuf),
 
+    /// A file is being dragged over the window.
+    ///
+    /// This event is produced for each file when the drag enters the window, and again every time
+    /// the cursor moves. The position is in pixels, relative to the top-left corner of the window.
+    HoveredFile(PathBuf, i32, i32),
+
+    /// The files that were dragged over the window left it without being dropped.
+    HoveredFileCancelled,
+
+    /// A drag started with `Window::start_drag` has ended.
+    ///
+    /// `accepted` is false if the data wasn't dropped, or if the target refused it.
+    DragFinished {
+        accepted: bool,
+        action: Option<DragAction>,
+    },
+
     /// The window received a unicode character.
     ReceivedCharacter(char),
 
@@ -22,14 +41,40 @@ pub enum Event {
     /// The parameter is true if the window has gained focus, and false if it has lost focus.
     Focused(bool),
 
+    /// The window entered or left fullscreen mode.
+    ///
+    /// The parameter is true if the window is now fullscreen, and false if it has left fullscreen.
+    Fullscreen(bool),
+
+    /// The window was maximized or restored from its maximized state.
+    ///
+    /// The parameter is true if the window is now maximized, and false if it has been restored.
+    Maximized(bool),
+
+    /// The window was minimized or restored from its minimized state.
+    ///
+    /// The parameter is true if the window is now minimized, and false if it has been restored.
+    Minimized(bool),
+
     /// An event from the keyboard has been received.
-    KeyboardInput(ElementState, ScanCode, Option<VirtualKeyCode>),
+    ///
+    /// The last parameter is true if the event was produced by the key being held down, rather
+    /// than by pressing it.
+    KeyboardInput(ElementState, ScanCode, Option<VirtualKeyCode>, bool),
 
     /// The cursor has moved on the window.
     ///
     /// The parameter are the (x,y) coords in pixels relative to the top-left corner of the window.
     MouseMoved(i32, i32),
 
+    /// The mouse has moved, possibly without moving the cursor.
+    ///
+    /// The parameters are the (x,y) distance in pixels, before being clipped by the edges of the
+    /// screen. This is the only way to follow the mouse while the cursor is locked.
+    ///
+    /// Only sent on Wayland.
+    MouseMotion(f64, f64),
+
     /// The cursor has entered the window.
     MouseEntered,
 
@@ -52,8 +97,14 @@ pub enum Event {
     /// The event loop was woken up by another thread.
     Awakened,
 
-    /// The window needs to be redrawn.
-    Refresh,
+    /// Parts of the window need to be redrawn, for example because they were uncovered.
+    ///
+    /// The rectangles cover the regions to redraw, which can be given as the damage of
+    /// `Window::present_pixels`.
+    Refresh(Vec<Rect>),
+
+    /// The time has come to draw the frame asked for with `Window::request_redraw`.
+    RedrawRequested,
 
     /// App has been suspended or resumed.
     ///
@@ -67,13 +67,17 @@ extern crate x11_dl;
 #[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "openbsd"))]
 #[macro_use(wayland_env,declare_handler)]
 extern crate wayland_client;
+#[cfg(any(target_os = "linux", target_os = "freebsd", target_os = "dragonfly", target_os = "openbsd"))]
+#[macro_use(ffi_dispatch)]
+extern crate wayland_sys;
 
 pub use events::*;
 pub use window::{WindowProxy, PollEventsIterator, WaitEventsIterator};
 pub use window::{AvailableMonitorsIter, MonitorId, get_available_monitors, get_primary_monitor};
 pub use native_monitor::NativeMonitorId;
 
-use std::os::raw::c_void;
+use std::os::raw::{c_int, c_ulong, c_void};
+use std::path::PathBuf;
 
 mod api;
 mod platform;
@@ -219,6 +223,207 @@ pub enum CursorState {
     ///
     /// This is useful for first-person cameras for example.
     Grab,
+
+    /// Hides the cursor and keeps it in place while the mouse moves, which is then reported with
+    /// `MouseMotion` events.
+    ///
+    /// This is useful for dragging a knob without hitting the edges of the screen for example.
+    /// Only supported on Wayland, the other platforms return an error.
+    Lock,
+}
+
+/// An image shown as the mouse cursor with `Window::set_custom_cursor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CustomCursor {
+    /// `width * height` pixels of 4 bytes, red, green, blue, then alpha, row by row starting
+    /// from the top-left corner. The alpha isn't premultiplied.
+    pub rgba: Vec<u8>,
+
+    /// The width of the image in pixels.
+    pub width: u32,
+
+    /// The height of the image in pixels.
+    pub height: u32,
+
+    /// The position of the pixel that clicks, from the top-left corner of the image.
+    pub hotspot: (u32, u32),
+}
+
+/// One size of the icon of a window, see `Window::set_window_icon`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Icon {
+    /// `width * height` pixels of 4 bytes, red, green, blue, then alpha, row by row starting
+    /// from the top-left corner. The alpha isn't premultiplied.
+    pub rgba: Vec<u8>,
+
+    /// The width of the image in pixels.
+    pub width: u32,
+
+    /// The height of the image in pixels.
+    pub height: u32,
+}
+
+/// Identifies one of the selections that hold data copied by the user.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// The clipboard, filled and read by explicit copy and paste actions.
+    Clipboard,
+
+    /// The primary selection, which holds the last selected text on Unix and is usually
+    /// pasted with the middle mouse button.
+    Primary,
+}
+
+/// Data that can be put in a selection, along with its format.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipboardData {
+    /// The MIME type of the data, for example `text/plain;charset=utf-8`.
+    pub mime_type: String,
+
+    /// The data itself.
+    pub data: Vec<u8>,
+}
+
+/// Data offered to other applications by a drag started with `Window::start_drag`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DragData {
+    /// A list of files, offered as `text/uri-list`.
+    Files(Vec<PathBuf>),
+
+    /// Arbitrary data, offered in one or several formats.
+    Data(Vec<ClipboardData>),
+}
+
+/// The action that the drop target performed with the dragged data.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DragAction {
+    /// The data was copied.
+    Copy,
+
+    /// The data was moved, so the source is expected to delete it.
+    Move,
+
+    /// The target created a link to the data.
+    Link,
+}
+
+/// Layout in memory of the pixels given to `Window::present_pixels`, 4 bytes per pixel.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PixelFormat {
+    /// Red, green, blue, then alpha.
+    Rgba8,
+
+    /// Blue, green, red, then alpha.
+    Bgra8,
+}
+
+/// The native handles of a window, for the libraries that draw to it or embed it.
+///
+/// The handles are valid as long as the `Window` they were returned by exists.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RawHandle {
+    /// A window created with xlib.
+    Xlib {
+        /// The XID of the window.
+        window: c_ulong,
+        /// The `Display*` of the connection to the X server.
+        display: *mut c_void,
+        /// The number of the screen the window is on.
+        screen: c_int,
+    },
+
+    /// A window used through XCB.
+    ///
+    /// The windows of winit are created with xlib and always returned as `Xlib`. Their
+    /// `xcb_connection_t` can be obtained with `XGetXCBConnection`.
+    Xcb {
+        /// The XID of the window.
+        window: u32,
+        /// The `xcb_connection_t*` of the connection to the X server.
+        connection: *mut c_void,
+        /// The number of the screen the window is on.
+        screen: c_int,
+    },
+
+    /// A Wayland surface.
+    Wayland {
+        /// The `wl_surface*` of the window.
+        surface: *mut c_void,
+        /// The `wl_display*` of the connection to the compositor.
+        display: *mut c_void,
+    },
+
+    /// A Win32 window.
+    Win32 {
+        /// The `HWND` of the window.
+        hwnd: *mut c_void,
+    },
+
+    /// A Cocoa window.
+    AppKit {
+        /// The `NSView*` that receives the events and holds the content of the window.
+        ns_view: *mut c_void,
+        /// The `NSWindow*` that contains the view. When embedded in a host, this is the window
+        /// of the host.
+        ns_window: *mut c_void,
+    },
+
+    /// The native window of an Android activity.
+    AndroidNdk {
+        /// The `ANativeWindow*` of the activity.
+        a_native_window: *mut c_void,
+    },
+
+    /// A UIKit window.
+    UiKit {
+        /// The `UIView*` that holds the content of the window.
+        ui_view: *mut c_void,
+        /// The `UIWindow*` that contains the view.
+        ui_window: *mut c_void,
+    },
+}
+
+/// A native window to create a window inside of, given to `WindowBuilder::with_parent`.
+///
+/// Building the window fails if the handle doesn't match the backend, or if the window it
+/// designates doesn't exist.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ParentHandle {
+    /// The XID of an X11 window, on the display winit connects to.
+    Xlib {
+        window: c_ulong,
+    },
+
+    /// The XID of an X11 window, as used by XCB, on the display winit connects to.
+    Xcb {
+        window: u32,
+    },
+
+    /// A `wl_surface*` of the host, along with the `wl_display*` of its connection. The window
+    /// is created as a subsurface of it, through the connection of the host.
+    Wayland {
+        surface: *mut c_void,
+        display: *mut c_void,
+    },
+
+    /// The `HWND` of a Win32 window.
+    Win32 {
+        hwnd: *mut c_void,
+    },
+
+    /// An `NSView*` to add the view of the window to.
+    AppKit {
+        ns_view: *mut c_void,
+    },
+}
+
+/// A rectangle in pixels, relative to the top-left corner of the window.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
 }
 
 /// Attributes to use when creating a window.
@@ -230,8 +435,11 @@ pub struct WindowAttributes {
     /// The default is `None`.
     pub dimensions: Option<(u32, u32)>,
 
-    /// Window parent handle.
-    pub parent: Option<*mut c_void>,
+    /// The native window to create the window inside of. If this is `None`, a top-level window
+    /// is created.
+    ///
+    /// The default is `None`.
+    pub parent: Option<ParentHandle>,
 
     /// The minimum dimensions a window can be, If this is `None`, the window will have no minimum dimensions (aside from reserved).
     ///
@@ -253,6 +461,12 @@ pub struct WindowAttributes {
     /// The default is `"glutin window"`.
     pub title: String,
 
+    /// The icon of the window, at one or more sizes. If this is empty, the system chooses the
+    /// icon.
+    ///
+    /// The default is empty.
+    pub window_icon: Vec<Icon>,
+
     /// Whether the window should be immediately visible upon creation.
     ///
     /// The default is `true`.
@@ -269,8 +483,9 @@ pub struct WindowAttributes {
     /// The default is `true`.
     pub decorations: bool,
 
-    /// [iOS only] Enable multitouch, see [UIView#multipleTouchEnabled]
-    /// (https://developer.apple.com/library/ios/documentation/UIKit/Reference/UIView_Class/#//apple_ref/occ/instp/UIView/multipleTouchEnabled)
+    /// Enable multitouch. On iOS, see [UIView#multipleTouchEnabled]
+    /// (https://developer.apple.com/library/ios/documentation/UIKit/Reference/UIView_Class/#//apple_ref/occ/instp/UIView/multipleTouchEnabled).
+    /// On X11 and Wayland, `Touch` events are only sent to windows that enable it.
     pub multitouch: bool,
 
     /// A function called upon resizing, necessary to receive resize events on Mac and possibly
@@ -288,6 +503,7 @@ impl Default for WindowAttributes {
             parent: None,
             monitor: None,
             title: "glutin window".to_owned(),
+            window_icon: Vec::new(),
             visible: true,
             transparent: false,
             decorations: true,
@@ -151,10 +151,72 @@ impl WindowExt for Window {
     }
 }
 
+/// Corresponds to the `_NET_WM_WINDOW_TYPE` hint of the EWMH specification.
+///
+/// The window manager uses it to decide how to decorate, place and stack the window.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum XWindowType {
+    /// A normal, top-level window. Corresponds to `_NET_WM_WINDOW_TYPE_NORMAL`.
+    Normal,
+    /// A small persistent window, such as a palette or toolbox.
+    /// Corresponds to `_NET_WM_WINDOW_TYPE_UTILITY`.
+    Utility,
+    /// A dialog window. Corresponds to `_NET_WM_WINDOW_TYPE_DIALOG`.
+    Dialog,
+    /// A tooltip. Corresponds to `_NET_WM_WINDOW_TYPE_TOOLTIP`.
+    Tooltip,
+    /// A popup menu. Corresponds to `_NET_WM_WINDOW_TYPE_POPUP_MENU`.
+    PopupMenu,
+    /// A splash screen displayed while an application is starting up.
+    /// Corresponds to `_NET_WM_WINDOW_TYPE_SPLASH`.
+    Splash,
+}
+
+impl Default for XWindowType {
+    fn default() -> Self {
+        XWindowType::Normal
+    }
+}
+
 /// Additional methods on `WindowBuilder` that are specific to Unix.
 pub trait WindowBuilderExt {
     fn with_x11_visual<T>(self, visual_infos: *const T) -> WindowBuilder;
     fn with_x11_screen(self, screen_id: i32) -> WindowBuilder;
+
+    /// Sets the `_NET_WM_WINDOW_TYPE` of the window. Only relevant on X11.
+    fn with_x11_window_type(self, window_type: XWindowType) -> WindowBuilder;
+
+    /// Marks the window as transient for another X window, for example the window of the
+    /// host that loaded a plugin. The window manager keeps it above that window.
+    ///
+    /// Only relevant on X11.
+    fn with_x11_transient_for(self, window: libc::c_ulong) -> WindowBuilder;
+
+    /// Sets `_NET_WM_STATE_MODAL`, which blocks the window it is transient for.
+    /// Only relevant on X11.
+    fn with_x11_modal(self, modal: bool) -> WindowBuilder;
+
+    /// Prevents the window from appearing in the taskbar. Only relevant on X11.
+    fn with_x11_skip_taskbar(self, skip_taskbar: bool) -> WindowBuilder;
+
+    /// Prevents the window from appearing in the pager. Only relevant on X11.
+    fn with_x11_skip_pager(self, skip_pager: bool) -> WindowBuilder;
+
+    /// Asks the window manager to keep the window above all the others. Only relevant on X11.
+    fn with_x11_always_on_top(self, always_on_top: bool) -> WindowBuilder;
+
+    /// Sets the instance and class names of `WM_CLASS`, by which the desktop groups the windows
+    /// of an application and finds its `.desktop` file. They default to the name of the
+    /// executable and that name capitalized. Creating the window fails if they contain NUL bytes.
+    /// Only relevant on X11.
+    fn with_x11_class(self, instance: String, class: String) -> WindowBuilder;
+
+    /// Completes the startup notification of the launcher of the application with this window,
+    /// by giving it the id in `DESKTOP_STARTUP_ID`. Only the first window of the process that
+    /// asks for it takes the id, and the variable is left in the environment. Off by default,
+    /// since a plugin would complete the startup of its host.
+    /// Only relevant on X11.
+    fn with_x11_startup_notification(self, startup_notification: bool) -> WindowBuilder;
 }
 
 impl WindowBuilderExt for WindowBuilder {
@@ -171,4 +233,52 @@ impl WindowBuilderExt for WindowBuilder {
         self.platform_specific.screen_id = Some(screen_id);
         self
     }
+
+    #[inline]
+    fn with_x11_window_type(mut self, window_type: XWindowType) -> WindowBuilder {
+        self.platform_specific.window_type = window_type;
+        self
+    }
+
+    #[inline]
+    fn with_x11_transient_for(mut self, window: libc::c_ulong) -> WindowBuilder {
+        self.platform_specific.transient_for = Some(window);
+        self
+    }
+
+    #[inline]
+    fn with_x11_modal(mut self, modal: bool) -> WindowBuilder {
+        self.platform_specific.modal = modal;
+        self
+    }
+
+    #[inline]
+    fn with_x11_skip_taskbar(mut self, skip_taskbar: bool) -> WindowBuilder {
+        self.platform_specific.skip_taskbar = skip_taskbar;
+        self
+    }
+
+    #[inline]
+    fn with_x11_skip_pager(mut self, skip_pager: bool) -> WindowBuilder {
+        self.platform_specific.skip_pager = skip_pager;
+        self
+    }
+
+    #[inline]
+    fn with_x11_always_on_top(mut self, always_on_top: bool) -> WindowBuilder {
+        self.platform_specific.always_on_top = always_on_top;
+        self
+    }
+
+    #[inline]
+    fn with_x11_class(mut self, instance: String, class: String) -> WindowBuilder {
+        self.platform_specific.class = Some((instance, class));
+        self
+    }
+
+    #[inline]
+    fn with_x11_startup_notification(mut self, startup_notification: bool) -> WindowBuilder {
+        self.platform_specific.startup_notification = startup_notification;
+        self
+    }
 }
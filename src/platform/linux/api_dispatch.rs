@@ -3,8 +3,16 @@ use std::sync::Arc;
 
 use CreationError;
 use CursorState;
+use CustomCursor;
 use Event;
+use Icon;
 use MouseCursor;
+use Selection;
+use ClipboardData;
+use ParentHandle;
+use PixelFormat;
+use RawHandle;
+use Rect;
 use WindowAttributes;
 use libc;
 
@@ -14,11 +22,20 @@ use api::x11::XConnection;
 use api::x11::XError;
 use api::x11::XNotSupported;
 use api::x11::ffi::XVisualInfo;
+use os::unix::XWindowType;
 
 #[derive(Clone, Default)]
 pub struct PlatformSpecificWindowBuilderAttributes {
     pub visual_infos: Option<XVisualInfo>,
     pub screen_id: Option<i32>,
+    pub window_type: XWindowType,
+    pub transient_for: Option<libc::c_ulong>,
+    pub modal: bool,
+    pub skip_taskbar: bool,
+    pub skip_pager: bool,
+    pub always_on_top: bool,
+    pub class: Option<(String, String)>,
+    pub startup_notification: bool,
 }
 
 pub enum Backend {
@@ -173,6 +190,15 @@ impl Window {
     pub fn new(window: &WindowAttributes, pl_attribs: &PlatformSpecificWindowBuilderAttributes)
                -> Result<Window, CreationError>
     {
+        // an embedded window must use the connection of its host
+        if let Some(ParentHandle::Wayland { display, .. }) = window.parent {
+            let ctxt = match wayland::WaylandContext::init_embedded(display) {
+                Some(ctxt) => ctxt,
+                None => return Err(CreationError::OsError("The Wayland display of the parent is null".to_string()))
+            };
+            return wayland::Window::new(ctxt, window).map(Window::Wayland);
+        }
+
         match *BACKEND {
             Backend::Wayland(ref ctxt) => {
                 wayland::Window::new(ctxt.clone(), window).map(Window::Wayland)
@@ -197,6 +223,144 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn set_fullscreen(&self, monitor: Option<MonitorId>) {
+        match self {
+            &Window::X(ref w) => w.set_fullscreen(monitor),
+            &Window::Wayland(ref w) => w.set_fullscreen(monitor)
+        }
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, decorations: bool) {
+        match self {
+            &Window::X(ref w) => w.set_decorations(decorations),
+            &Window::Wayland(ref w) => w.set_decorations(decorations)
+        }
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        match self {
+            &Window::X(ref w) => w.set_min_dimensions(dimensions),
+            &Window::Wayland(ref w) => w.set_min_dimensions(dimensions)
+        }
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        match self {
+            &Window::X(ref w) => w.set_max_dimensions(dimensions),
+            &Window::Wayland(ref w) => w.set_max_dimensions(dimensions)
+        }
+    }
+
+    #[inline]
+    pub fn set_resizable(&self, resizable: bool) {
+        match self {
+            &Window::X(ref w) => w.set_resizable(resizable),
+            &Window::Wayland(ref w) => w.set_resizable(resizable)
+        }
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        match self {
+            &Window::X(ref w) => w.set_aspect_ratio(ratio),
+            &Window::Wayland(ref w) => w.set_aspect_ratio(ratio)
+        }
+    }
+
+    #[inline]
+    pub fn set_resize_increments(&self, increments: Option<(u32, u32)>) {
+        match self {
+            &Window::X(ref w) => w.set_resize_increments(increments),
+            &Window::Wayland(ref w) => w.set_resize_increments(increments)
+        }
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, selection: Selection, contents: Vec<ClipboardData>) {
+        match self {
+            &Window::X(ref w) => w.set_clipboard(selection, contents),
+            &Window::Wayland(ref w) => w.set_clipboard(selection, contents)
+        }
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, selection: Selection, mime_type: &str) -> Option<Vec<u8>> {
+        match self {
+            &Window::X(ref w) => w.get_clipboard(selection, mime_type),
+            &Window::Wayland(ref w) => w.get_clipboard(selection, mime_type)
+        }
+    }
+
+    #[inline]
+    pub fn get_clipboard_mime_types(&self, selection: Selection) -> Vec<String> {
+        match self {
+            &Window::X(ref w) => w.get_clipboard_mime_types(selection),
+            &Window::Wayland(ref w) => w.get_clipboard_mime_types(selection)
+        }
+    }
+
+    #[inline]
+    pub fn request_redraw(&self) {
+        match self {
+            &Window::X(ref w) => w.request_redraw(),
+            &Window::Wayland(ref w) => w.request_redraw()
+        }
+    }
+
+    #[inline]
+    pub fn present_pixels(&self, pixels: &[u8], width: u32, height: u32, format: PixelFormat,
+                          damage: Option<&[Rect]>)
+    {
+        match self {
+            &Window::X(ref w) => w.present_pixels(pixels, width, height, format, damage),
+            &Window::Wayland(ref w) => w.present_pixels(pixels, width, height, format, damage)
+        }
+    }
+
+    #[inline]
+    pub fn start_drag(&self, contents: Vec<ClipboardData>) {
+        match self {
+            &Window::X(ref w) => w.start_drag(contents),
+            &Window::Wayland(ref w) => w.start_drag(contents)
+        }
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        match self {
+            &Window::X(ref w) => w.set_maximized(maximized),
+            &Window::Wayland(ref w) => w.set_maximized(maximized)
+        }
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        match self {
+            &Window::X(ref w) => w.set_minimized(minimized),
+            &Window::Wayland(ref w) => w.set_minimized(minimized)
+        }
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        match self {
+            &Window::X(ref w) => w.is_maximized(),
+            &Window::Wayland(ref w) => w.is_maximized()
+        }
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        match self {
+            &Window::X(ref w) => w.is_minimized(),
+            &Window::Wayland(ref w) => w.is_minimized()
+        }
+    }
+
     #[inline]
     pub fn show(&self) {
         match self {
@@ -293,6 +457,30 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn set_window_icon(&self, icon: &[Icon]) -> Result<(), CreationError> {
+        match self {
+            &Window::X(ref w) => w.set_window_icon(icon),
+            &Window::Wayland(ref w) => w.set_window_icon(icon)
+        }
+    }
+
+    #[inline]
+    pub fn request_user_attention(&self) {
+        match self {
+            &Window::X(ref w) => w.request_user_attention(),
+            &Window::Wayland(ref w) => w.request_user_attention()
+        }
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        match self {
+            &Window::X(ref w) => w.set_custom_cursor(cursor),
+            &Window::Wayland(ref w) => w.set_custom_cursor(cursor)
+        }
+    }
+
     #[inline]
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
         match self {
@@ -317,6 +505,18 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn raw_handle(&self) -> Option<RawHandle> {
+        use wayland_client::Proxy;
+        Some(match self {
+            &Window::X(ref w) => w.raw_handle(),
+            &Window::Wayland(ref w) => RawHandle::Wayland {
+                surface: w.get_surface().ptr() as *mut _,
+                display: w.get_display().ptr() as *mut _
+            }
+        })
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         use wayland_client::Proxy;
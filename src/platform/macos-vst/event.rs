@@ -1,5 +1,6 @@
 use cocoa::appkit::{self, NSApplication, NSColor, NSEvent, NSView, NSWindow};
 use cocoa::base::{id, nil, YES};
+use objc::runtime::BOOL;
 use cocoa::foundation::{NSAutoreleasePool, NSDate, NSDefaultRunLoopMode, NSPoint, NSRect, NSSize,
                         NSString, NSUInteger};
 use events;
@@ -60,7 +61,10 @@ pub unsafe fn NSEventToEvent(nsevent: id) -> Option<Event> {
             }
 
             let vkey =  vkeycode_to_element(NSEvent::keyCode(nsevent));
-            events.push_back(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(nsevent) as u8, vkey));
+            // set when the key is held down
+            let is_repeat: BOOL = msg_send![nsevent, isARepeat];
+            events.push_back(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(nsevent) as u8, vkey,
+                                                  is_repeat == YES));
             let event = events.pop_front();
             // window.delegate.state.pending_events.lock().unwrap().extend(events.into_iter());
             event
@@ -68,7 +72,7 @@ pub unsafe fn NSEventToEvent(nsevent: id) -> Option<Event> {
         appkit::NSKeyUp => {
             let vkey =  vkeycode_to_element(NSEvent::keyCode(nsevent));
 
-            Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(nsevent) as u8, vkey))
+            Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(nsevent) as u8, vkey, false))
         },
         appkit::NSFlagsChanged => {
             let mut events = VecDeque::new();
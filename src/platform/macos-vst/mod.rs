@@ -12,8 +12,16 @@ use os::macos::{ ActivationPolicy, WindowExt };
 
 use CreationError;
 use CursorState;
+use CustomCursor;
 use Event;
+use Icon;
 use MouseCursor;
+use Selection;
+use ClipboardData;
+use PixelFormat;
+use ParentHandle;
+use RawHandle;
+use Rect;
 use WindowAttributes;
 
 mod idref;
@@ -201,8 +209,8 @@ impl Window {
         info!("Winit logging started. Attaching new handle.");
 
         match win_attribs.parent {
-            Some(parent) => {
-                let host_view_id = parent as id;
+            Some(ParentHandle::AppKit { ns_view }) if !ns_view.is_null() => {
+                let host_view_id = ns_view as id;
                 let window = unsafe{ msg_send![host_view_id, window] };
 
                 let view: id = unsafe { msg_send![responder::get_window_responder_class(), new] };
@@ -233,6 +241,7 @@ impl Window {
                     pending_events: Mutex::new(pending_events),
                 })
             },
+            Some(other) => Err(CreationError::OsError(format!("Expected a non-null NSView as parent, got {:?}", other))),
             None => Err(CreationError::OsError("Parent view is null.".to_string()))
         }
     }
@@ -242,6 +251,95 @@ impl Window {
         error!("set_title() not supported.");
     }
 
+    #[inline]
+    pub fn set_fullscreen(&self, _monitor: Option<MonitorId>) {
+        error!("set_fullscreen() not supported.");
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _decorations: bool) {
+        error!("set_decorations() not supported.");
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _dimensions: Option<(u32, u32)>) {
+        error!("set_min_dimensions() not supported.");
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _dimensions: Option<(u32, u32)>) {
+        error!("set_max_dimensions() not supported.");
+    }
+
+    #[inline]
+    pub fn set_resizable(&self, _resizable: bool) {
+        error!("set_resizable() not supported.");
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _ratio: Option<(u32, u32)>) {
+        error!("set_aspect_ratio() not supported.");
+    }
+
+    #[inline]
+    pub fn set_resize_increments(&self, _increments: Option<(u32, u32)>) {
+        error!("set_resize_increments() not supported.");
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, _selection: Selection, _contents: Vec<ClipboardData>) {
+        error!("set_clipboard() not supported.");
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, _selection: Selection, _mime_type: &str) -> Option<Vec<u8>> {
+        error!("get_clipboard() not supported.");
+        None
+    }
+
+    #[inline]
+    pub fn get_clipboard_mime_types(&self, _selection: Selection) -> Vec<String> {
+        error!("get_clipboard_mime_types() not supported.");
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn request_redraw(&self) {
+        error!("request_redraw() not supported.");
+    }
+
+    #[inline]
+    pub fn present_pixels(&self, _pixels: &[u8], _width: u32, _height: u32, _format: PixelFormat,
+                          _damage: Option<&[Rect]>)
+    {
+        error!("present_pixels() not supported.");
+    }
+
+    #[inline]
+    pub fn start_drag(&self, _contents: Vec<ClipboardData>) {
+        error!("start_drag() not supported.");
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, _maximized: bool) {
+        error!("set_maximized() not supported.");
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, _minimized: bool) {
+        error!("set_minimized() not supported.");
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        false
+    }
+
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
         unsafe {
@@ -309,6 +407,14 @@ impl Window {
     #[inline]
     pub fn hide(&self) {}
 
+    #[inline]
+    pub fn raw_handle(&self) -> Option<RawHandle> {
+        Some(RawHandle::AppKit {
+            ns_view: *self.view as *mut libc::c_void,
+            ns_window: *self.window as *mut libc::c_void,
+        })
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         *self.view as *mut libc::c_void
@@ -328,9 +434,29 @@ impl Window {
     pub fn set_cursor(&self, cursor: MouseCursor) {
     }
 
+    #[inline]
+    pub fn set_window_icon(&self, _icon: &[Icon]) -> Result<(), CreationError> {
+        error!("set_window_icon() not supported.");
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn request_user_attention(&self) {
+        error!("request_user_attention() not supported.");
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, _cursor: &CustomCursor) {
+        error!("set_custom_cursor() not supported.");
+    }
+
     #[inline]
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
-        Ok(())
+        match state {
+            // the motion of the mouse isn't reported with `MouseMotion` events
+            CursorState::Lock => Err("Cursor cannot be locked on macOS yet.".to_string()),
+            _ => Ok(())
+        }
     }
 
     #[inline]
@@ -667,9 +667,9 @@ impl Window {
 
     unsafe fn modifier_event(event: id, keymask: appkit::NSEventModifierFlags, key: events::VirtualKeyCode, key_pressed: bool) -> Option<Event> {
         if !key_pressed && NSEvent::modifierFlags(event).contains(keymask) {
-            return Some(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(event) as u8, Some(key)));
+            return Some(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(event) as u8, Some(key), false));
         } else if key_pressed && !NSEvent::modifierFlags(event).contains(keymask) {
-            return Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(event) as u8, Some(key)));
+            return Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(event) as u8, Some(key), false));
         }
 
         return None;
@@ -745,6 +745,11 @@ impl Window {
             CursorState::Grab => {
                 let _: i32 = unsafe { CGAssociateMouseAndMouseCursorPosition(false) };
                 Ok(())
+            },
+            CursorState::Lock => {
+                let _: () = unsafe { msg_send![cls, hide] };
+                let _: i32 = unsafe { CGAssociateMouseAndMouseCursorPosition(false) };
+                Ok(())
             }
         }
     }
@@ -920,7 +925,10 @@ unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
             }
 
             let vkey =  event::vkeycode_to_element(NSEvent::keyCode(nsevent));
-            events.push_back(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(nsevent) as u8, vkey));
+            // set when the key is held down
+            let is_repeat: BOOL = msg_send![nsevent, isARepeat];
+            events.push_back(Event::KeyboardInput(ElementState::Pressed, NSEvent::keyCode(nsevent) as u8, vkey,
+                                                  is_repeat == YES));
             let event = events.pop_front();
             window.delegate.state.pending_events.lock().unwrap().extend(events.into_iter());
             event
@@ -928,7 +936,7 @@ unsafe fn NSEventToEvent(window: &Window, nsevent: id) -> Option<Event> {
         appkit::NSKeyUp => {
             let vkey =  event::vkeycode_to_element(NSEvent::keyCode(nsevent));
 
-            Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(nsevent) as u8, vkey))
+            Some(Event::KeyboardInput(ElementState::Released, NSEvent::keyCode(nsevent) as u8, vkey, false))
         },
         appkit::NSFlagsChanged => {
             let mut events = VecDeque::new();
@@ -5,8 +5,15 @@ use std::sync::Arc;
 
 use CreationError;
 use CursorState;
+use CustomCursor;
 use Event;
+use Icon;
 use MouseCursor;
+use Selection;
+use ClipboardData;
+use PixelFormat;
+use RawHandle;
+use Rect;
 use WindowAttributes;
 use libc;
 
@@ -97,6 +104,78 @@ impl Window {
     pub fn set_title(&self, _title: &str) {
     }
 
+    #[inline]
+    pub fn set_fullscreen(&self, _: Option<MonitorId>) {
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_resizable(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_resize_increments(&self, _: Option<(u32, u32)>) {
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, _: Selection, _: Vec<ClipboardData>) {
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, _: Selection, _: &str) -> Option<Vec<u8>> {
+        None
+    }
+
+    #[inline]
+    pub fn get_clipboard_mime_types(&self, _: Selection) -> Vec<String> {
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn request_redraw(&self) {
+    }
+
+    #[inline]
+    pub fn present_pixels(&self, _: &[u8], _: u32, _: u32, _: PixelFormat, _: Option<&[Rect]>) {
+    }
+
+    #[inline]
+    pub fn start_drag(&self, _: Vec<ClipboardData>) {
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, _: bool) {
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        false
+    }
+
     #[inline]
     pub fn get_position(&self) -> Option<(i32, i32)> {
         Some((0, 0))
@@ -143,6 +222,11 @@ impl Window {
     #[inline]
     pub fn hide(&self) {}
 
+    #[inline]
+    pub fn raw_handle(&self) -> Option<RawHandle> {
+        None
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut libc::c_void {
         unimplemented!()
@@ -161,6 +245,19 @@ impl Window {
     pub fn set_cursor(&self, cursor: MouseCursor) {
     }
 
+    #[inline]
+    pub fn set_window_icon(&self, _: &[Icon]) -> Result<(), CreationError> {
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn request_user_attention(&self) {
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, _: &CustomCursor) {
+    }
+
     #[inline]
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
         Ok(())
@@ -201,7 +201,9 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
                 user32::DefWindowProcW(window, msg, wparam, lparam)
             } else {
                 let (scancode, vkey) = event::vkeycode_to_element(wparam, lparam);
-                send_event(window, KeyboardInput(Pressed, scancode, vkey));
+                // bit 30 is the previous state of the key, set when the key is held down
+                let is_repeat = lparam & (1 << 30) != 0;
+                send_event(window, KeyboardInput(Pressed, scancode, vkey, is_repeat));
                 0
             }
         },
@@ -210,7 +212,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
             use events::Event::KeyboardInput;
             use events::ElementState::Released;
             let (scancode, vkey) = event::vkeycode_to_element(wparam, lparam);
-            send_event(window, KeyboardInput(Released, scancode, vkey));
+            send_event(window, KeyboardInput(Released, scancode, vkey, false));
             0
         },
 
@@ -325,7 +327,7 @@ pub unsafe extern "system" fn callback(window: winapi::HWND, msg: winapi::UINT,
                                             ptr::null_mut(),
                                             window_state.cursor));
                                 },
-                                CursorState::Grab | CursorState::Hide => {
+                                CursorState::Grab | CursorState::Hide | CursorState::Lock => {
                                     user32::SetCursor(ptr::null_mut());
                                 }
                             }
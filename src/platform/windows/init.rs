@@ -14,6 +14,7 @@ use super::PlatformSpecificWindowBuilderAttributes;
 use CreationError;
 use CreationError::OsError;
 use CursorState;
+use ParentHandle;
 use WindowAttributes;
 use platform;
 
@@ -39,7 +40,11 @@ pub fn new_window(window: &WindowAttributes, pl_attribs: &PlatformSpecificWindow
 
     let window2 = WindowAttributes2::from(window.clone());
 
-    let parent = window.parent.unwrap_or(ptr::null_mut());
+    let parent = match window.parent {
+        Some(ParentHandle::Win32 { hwnd }) if !hwnd.is_null() => hwnd,
+        Some(other) => return Err(OsError(format!("Expected the HWND of a window as parent, got {:?}", other))),
+        None => ptr::null_mut(),
+    };
     if !parent.is_null() {
         unsafe {
             // creating and sending the `Window`
@@ -193,7 +198,11 @@ unsafe fn init(title: Vec<u16>, window: &WindowAttributes2, _pl_attribs: Platfor
         decorations: window.decorations,
         multitouch: window.multitouch,
         resize_callback: window.resize_callback,
-        parent: Some(maybe_parent as *mut c_void),
+        parent: if maybe_parent.is_null() {
+            None
+        } else {
+            Some(ParentHandle::Win32 { hwnd: maybe_parent as *mut c_void })
+        },
     };
 
     // Creating a mutex to track the current window state
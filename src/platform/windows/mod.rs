@@ -10,8 +10,10 @@ use std::sync::{
     Mutex
 };
 use std::sync::mpsc::Receiver;
-use {CreationError, Event, MouseCursor};
+use {CreationError, Event, MouseCursor, Selection, ClipboardData, PixelFormat, RawHandle, Rect};
 use CursorState;
+use CustomCursor;
+use Icon;
 
 use WindowAttributes;
 
@@ -117,6 +119,115 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn set_fullscreen(&self, _monitor: Option<MonitorId>) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_decorations(&self, _decorations: bool) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_min_dimensions(&self, _dimensions: Option<(u32, u32)>) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_max_dimensions(&self, _dimensions: Option<(u32, u32)>) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_resizable(&self, _resizable: bool) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_aspect_ratio(&self, _ratio: Option<(u32, u32)>) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_resize_increments(&self, _increments: Option<(u32, u32)>) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_clipboard(&self, _selection: Selection, _contents: Vec<ClipboardData>) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn get_clipboard(&self, _selection: Selection, _mime_type: &str) -> Option<Vec<u8>> {
+        // TODO
+        None
+    }
+
+    #[inline]
+    pub fn get_clipboard_mime_types(&self, _selection: Selection) -> Vec<String> {
+        // TODO
+        Vec::new()
+    }
+
+    #[inline]
+    pub fn request_redraw(&self) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn present_pixels(&self, _pixels: &[u8], _width: u32, _height: u32, _format: PixelFormat,
+                          _damage: Option<&[Rect]>)
+    {
+        // TODO
+    }
+
+    #[inline]
+    pub fn start_drag(&self, _contents: Vec<ClipboardData>) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        unsafe {
+            user32::ShowWindow(self.window.0, if maximized { winapi::SW_MAXIMIZE }
+                                              else { winapi::SW_RESTORE });
+        }
+    }
+
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        unsafe {
+            user32::ShowWindow(self.window.0, if minimized { winapi::SW_MINIMIZE }
+                                              else { winapi::SW_RESTORE });
+        }
+    }
+
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.get_show_cmd() == Some(winapi::SW_SHOWMAXIMIZED)
+    }
+
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        self.get_show_cmd() == Some(winapi::SW_SHOWMINIMIZED)
+    }
+
+    /// Returns the current show state of the window, as reported by GetWindowPlacement.
+    fn get_show_cmd(&self) -> Option<c_int> {
+        use std::mem;
+
+        let mut placement: winapi::WINDOWPLACEMENT = unsafe { mem::zeroed() };
+        placement.length = mem::size_of::<winapi::WINDOWPLACEMENT>() as winapi::UINT;
+
+        if unsafe { user32::GetWindowPlacement(self.window.0, &mut placement) } == 0 {
+            return None
+        }
+
+        Some(placement.showCmd as c_int)
+    }
+
     #[inline]
     pub fn show(&self) {
         unsafe {
@@ -224,6 +335,11 @@ impl Window {
         }
     }
 
+    #[inline]
+    pub fn raw_handle(&self) -> Option<RawHandle> {
+        Some(RawHandle::Win32 { hwnd: self.window.0 as *mut ::libc::c_void })
+    }
+
     #[inline]
     pub fn platform_display(&self) -> *mut ::libc::c_void {
         // What should this return on win32?
@@ -264,6 +380,22 @@ impl Window {
         cur.cursor = cursor_id;
     }
 
+    #[inline]
+    pub fn set_window_icon(&self, _icon: &[Icon]) -> Result<(), CreationError> {
+        // TODO
+        Err(CreationError::NotSupported)
+    }
+
+    #[inline]
+    pub fn request_user_attention(&self) {
+        // TODO
+    }
+
+    #[inline]
+    pub fn set_custom_cursor(&self, _cursor: &CustomCursor) {
+        // TODO
+    }
+
 
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
         let mut current_state = self.window_state.lock().unwrap();
@@ -278,6 +410,8 @@ impl Window {
             (CursorState::Hide, CursorState::Hide) => Ok(()),
             (CursorState::Grab, CursorState::Grab) => Ok(()),
 
+            (CursorState::Lock, _) => Err(format!("Cursor cannot be locked on Windows yet")),
+
             (CursorState::Hide, CursorState::Normal) => {
                 current_state.cursor_state = CursorState::Hide;
                 Ok(())
@@ -1,9 +1,18 @@
 use std::collections::vec_deque::IntoIter as VecDequeIter;
 
+use ClipboardData;
 use CreationError;
 use CursorState;
+use CustomCursor;
+use DragData;
 use Event;
+use Icon;
 use MouseCursor;
+use ParentHandle;
+use PixelFormat;
+use RawHandle;
+use Rect;
+use Selection;
 use Window;
 use WindowBuilder;
 use native_monitor::NativeMonitorId;
@@ -11,7 +20,14 @@ use native_monitor::NativeMonitorId;
 use libc;
 use platform;
 
-use std::os::raw::c_void;
+use std::borrow::Cow;
+use std::env;
+use std::path::{Path, PathBuf};
+
+// The MIME types under which text is offered, in order of preference. `UTF8_STRING` is the
+// name used by X11 clients.
+const TEXT_MIME_TYPES: &'static [&'static str] = &["text/plain;charset=utf-8", "UTF8_STRING",
+                                                   "text/plain"];
 
 impl WindowBuilder {
     /// Initializes a new `WindowBuilder` with default values.
@@ -23,8 +39,10 @@ impl WindowBuilder {
         }
     }
 
+    /// Creates the window inside of a native window, for example to embed it in the editor of a
+    /// plugin host.
     #[inline]
-    pub fn with_parent(mut self, parent: *mut c_void) -> WindowBuilder {
+    pub fn with_parent(mut self, parent: ParentHandle) -> WindowBuilder {
         self.window.parent = Some(parent);
         self
     }
@@ -87,6 +105,16 @@ impl WindowBuilder {
         self
     }
 
+    /// Requests a specific icon for the window, given at one or more sizes. The system picks the
+    /// size that suits it best.
+    ///
+    /// See `Window::set_window_icon` for details.
+    #[inline]
+    pub fn with_window_icon(mut self, icon: Vec<Icon>) -> WindowBuilder {
+        self.window.window_icon = icon;
+        self
+    }
+
     /// Sets whether the window should have a border, a title bar, etc.
     #[inline]
     pub fn with_decorations(mut self, decorations: bool) -> WindowBuilder {
@@ -128,6 +156,10 @@ impl WindowBuilder {
             }
         }
 
+        for image in &self.window.window_icon {
+            check_icon(image);
+        }
+
         // building
         let mut w = try!(platform::Window::new(&self.window, &self.platform_specific));
 
@@ -200,6 +232,139 @@ impl Window {
         self.window.hide()
     }
 
+    /// Puts the window in fullscreen mode on the given monitor, or restores it to windowed mode
+    /// if `monitor` is `None`.
+    ///
+    /// An `Event::Fullscreen` is produced once the change has been applied.
+    ///
+    /// This is a no-op if the window has already been closed.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    /// - On X11, a window can't be moved to another X screen, so it goes fullscreen on the screen
+    ///   it was created on.
+    /// - On X11, the video mode of the screen is left as it is. Only a window created fullscreen
+    ///   switches it, and switches it back when it leaves fullscreen or is destroyed.
+    ///
+    #[inline]
+    pub fn set_fullscreen(&self, monitor: Option<MonitorId>) {
+        self.window.set_fullscreen(monitor.map(|MonitorId(monitor)| monitor))
+    }
+
+    /// Shows or hides the title bar and borders drawn around the window.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    /// - On X11, this relies on the window manager honoring the Motif hints.
+    ///
+    #[inline]
+    pub fn set_decorations(&self, decorations: bool) {
+        self.window.set_decorations(decorations)
+    }
+
+    /// Sets the minimum dimensions of the window, or removes the limit if `None`.
+    ///
+    /// Width and height are in pixels.
+    #[inline]
+    pub fn set_min_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window.set_min_dimensions(dimensions)
+    }
+
+    /// Sets the maximum dimensions of the window, or removes the limit if `None`.
+    ///
+    /// Width and height are in pixels.
+    #[inline]
+    pub fn set_max_dimensions(&self, dimensions: Option<(u32, u32)>) {
+        self.window.set_max_dimensions(dimensions)
+    }
+
+    /// Sets whether the user can resize the window.
+    ///
+    /// A window that isn't resizable keeps the size it had when this was called.
+    #[inline]
+    pub fn set_resizable(&self, resizable: bool) {
+        self.window.set_resizable(resizable)
+    }
+
+    /// Locks the ratio between the width and the height of the window to
+    /// `numerator / denominator`, or removes the lock if `None`.
+    #[inline]
+    pub fn set_aspect_ratio(&self, ratio: Option<(u32, u32)>) {
+        self.window.set_aspect_ratio(ratio)
+    }
+
+    /// Makes the window size snap to multiples of `(width, height)` pixels, counted from the
+    /// minimum dimensions. `None` removes the increments.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    /// - On X11, all these constraints are enforced by the window manager.
+    /// - On Wayland, the compositor doesn't know about them, so they are only applied when the
+    ///   window is resized.
+    ///
+    #[inline]
+    pub fn set_resize_increments(&self, increments: Option<(u32, u32)>) {
+        self.window.set_resize_increments(increments)
+    }
+
+    /// Maximizes the window, or restores it to its previous size.
+    ///
+    /// An `Event::Maximized` is produced once the window manager has applied the change, or
+    /// when the user maximizes or restores the window by other means.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11, Windows and Wayland.
+    /// - No `Event::Maximized` is produced on Windows.
+    /// - On Wayland, only windows of xdg_shell can be maximized, not the ones of wl_shell.
+    ///
+    #[inline]
+    pub fn set_maximized(&self, maximized: bool) {
+        self.window.set_maximized(maximized)
+    }
+
+    /// Minimizes (iconifies) the window, or restores it.
+    ///
+    /// An `Event::Minimized` is produced once the window manager has applied the change, or
+    /// when the user minimizes or restores the window by other means.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11, Windows and Wayland.
+    /// - No `Event::Minimized` is produced on Windows and Wayland.
+    /// - On Wayland, only windows of xdg_shell can be minimized, and they can't be restored by
+    ///   the application, only by the user.
+    ///
+    #[inline]
+    pub fn set_minimized(&self, minimized: bool) {
+        self.window.set_minimized(minimized)
+    }
+
+    /// Returns true if the window is currently maximized.
+    ///
+    /// Always returns `false` on platforms where `set_maximized` isn't implemented.
+    #[inline]
+    pub fn is_maximized(&self) -> bool {
+        self.window.is_maximized()
+    }
+
+    /// Returns true if the window is currently minimized.
+    ///
+    /// Always returns `false` on platforms where `set_minimized` isn't implemented.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Always returns `false` on Wayland, where the compositor doesn't tell when a window is
+    ///   minimized.
+    ///
+    #[inline]
+    pub fn is_minimized(&self) -> bool {
+        self.window.is_minimized()
+    }
+
     /// Returns the position of the top-left hand corner of the window relative to the
     ///  top-left hand corner of the desktop.
     ///
@@ -305,7 +470,18 @@ impl Window {
         WaitEventsIterator(self.window.wait_events())
     }
 
-    /// DEPRECATED. Gets the native platform specific display for this window.
+    /// Returns the native handles of the window, or `None` if the platform has none.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Emscripten has no handles.
+    ///
+    #[inline]
+    pub fn raw_handle(&self) -> Option<RawHandle> {
+        self.window.raw_handle()
+    }
+
+    /// DEPRECATED, use `raw_handle` instead. Gets the native platform specific display for this window.
     /// This is typically only required when integrating with
     /// other libraries that need this information.
     #[inline]
@@ -313,7 +489,7 @@ impl Window {
         self.window.platform_display()
     }
 
-    /// DEPRECATED. Gets the native platform specific window handle. This is
+    /// DEPRECATED, use `raw_handle` instead. Gets the native platform specific window handle. This is
     /// typically only required when integrating with other libraries
     /// that need this information.
     #[inline]
@@ -338,12 +514,67 @@ impl Window {
         self.window.set_window_resize_callback(callback);
     }
 
+    /// Changes the icon of the window, given at one or more sizes. The system picks the size that
+    /// suits it best. With no image, the system chooses the icon.
+    ///
+    /// Sizes that are common in taskbars and window switchers are 16, 32, 48 and 64 pixels.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    /// - On Wayland, this requires the `xdg_toplevel_icon_v1` protocol, and images that aren't
+    ///   square are ignored. `CreationError::NotSupported` is returned if the compositor doesn't
+    ///   support it.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if the pixel buffer of an image is smaller than its dimensions.
+    pub fn set_window_icon(&self, icon: &[Icon]) -> Result<(), CreationError> {
+        for image in icon {
+            check_icon(image);
+        }
+        self.window.set_window_icon(icon)
+    }
+
+    /// Asks the window manager to draw the attention of the user to the window, for example by
+    /// flashing its entry in the taskbar. The request ends once the window gets the focus.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11, where it sets the urgency hint and
+    ///   `_NET_WM_STATE_DEMANDS_ATTENTION`.
+    #[inline]
+    pub fn request_user_attention(&self) {
+        self.window.request_user_attention()
+    }
+
     /// Modifies the mouse cursor of the window.
     /// Has no effect on Android.
     pub fn set_cursor(&self, cursor: MouseCursor) {
         self.window.set_cursor(cursor);
     }
 
+    /// Shows an image as the mouse cursor of the window, until the next call to `set_cursor` or
+    /// `set_custom_cursor`.
+    ///
+    /// The last images used are kept, so switching back to one of them is cheap.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if `rgba` is too small for the dimensions of the image, or if the hotspot is
+    /// outside of it.
+    pub fn set_custom_cursor(&self, cursor: &CustomCursor) {
+        assert!(cursor.rgba.len() >= cursor.width as usize * cursor.height as usize * 4,
+                "The pixel buffer is smaller than the dimensions of the cursor");
+        assert!(cursor.hotspot.0 < cursor.width && cursor.hotspot.1 < cursor.height,
+                "The hotspot is outside of the cursor");
+        self.window.set_custom_cursor(cursor);
+    }
+
     /// Returns the ratio between the backing framebuffer resolution and the
     /// window size in screen pixels. This is typically one for a normal display
     /// and two for a retina display.
@@ -365,6 +596,167 @@ impl Window {
     pub fn set_cursor_state(&self, state: CursorState) -> Result<(), String> {
         self.window.set_cursor_state(state)
     }
+
+    /// Puts data in a selection, replacing its previous contents.
+    ///
+    /// The same data can be offered in several formats, for example as a custom MIME type and
+    /// as text. Other applications pick the format they understand.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    /// - On X11, the data is served by the event loop, so it is only available to other
+    ///   applications while events are being polled.
+    /// - On Wayland, the primary selection needs the `zwp_primary_selection_device_manager_v1`
+    ///   extension. Without it, the primary selection stays empty.
+    ///
+    #[inline]
+    pub fn set_clipboard(&self, selection: Selection, contents: Vec<ClipboardData>) {
+        self.window.set_clipboard(selection, contents)
+    }
+
+    /// Returns the contents of a selection in the given format, or `None` if the selection is
+    /// empty or can't be converted to this format.
+    ///
+    /// This blocks until the application owning the selection has sent the data.
+    #[inline]
+    pub fn get_clipboard(&self, selection: Selection, mime_type: &str) -> Option<Vec<u8>> {
+        self.window.get_clipboard(selection, mime_type)
+    }
+
+    /// Returns the formats in which the contents of a selection are available.
+    #[inline]
+    pub fn get_clipboard_mime_types(&self, selection: Selection) -> Vec<String> {
+        self.window.get_clipboard_mime_types(selection)
+    }
+
+    /// Puts text in a selection. This is a shortcut for `set_clipboard` with the usual text
+    /// formats.
+    pub fn set_clipboard_text(&self, selection: Selection, text: &str) {
+        let contents = TEXT_MIME_TYPES.iter().map(|mime_type| ClipboardData {
+            mime_type: mime_type.to_string(),
+            data: text.as_bytes().to_vec(),
+        }).collect();
+        self.window.set_clipboard(selection, contents)
+    }
+
+    /// Starts dragging data out of the window. This should be called while a mouse button is
+    /// pressed, usually in response to the cursor moving with the button held.
+    ///
+    /// An `Event::DragFinished` is produced when the user releases the button.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    ///
+    pub fn start_drag(&self, data: DragData) {
+        let contents = match data {
+            DragData::Files(paths) => vec![ClipboardData {
+                mime_type: "text/uri-list".to_owned(),
+                data: file_uri_list(&paths),
+            }],
+            DragData::Data(contents) => contents,
+        };
+        self.window.start_drag(contents)
+    }
+
+    /// Asks for an `Event::RedrawRequested` when it is time to draw the next frame. Several
+    /// calls before that produce a single event.
+    ///
+    /// The window should be drawn in response to the event, as the next frame is paced by the
+    /// one being drawn. Redraws are slowed down or stopped while the window isn't visible.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    /// - X11 uses the Present extension when available, otherwise redraws are limited to 60 per
+    ///   second.
+    ///
+    #[inline]
+    pub fn request_redraw(&self) {
+        self.window.request_redraw()
+    }
+
+    /// Draws pixels rendered on the CPU to the window, without going through OpenGL.
+    ///
+    /// `pixels` holds `width * height` pixels of 4 bytes, row by row starting from the top-left
+    /// corner. If `damage` is `Some`, only these rectangles are updated on the screen; the buffer
+    /// must still contain the whole image. The first call and every call with a new size update
+    /// the whole window.
+    ///
+    /// ## Platform-specific
+    ///
+    /// - Only implemented on X11 and Wayland.
+    /// - On Wayland, the frame is skipped if the compositor still holds all the buffers of the
+    ///   window. An `Event::RedrawRequested` is produced once one of them is released.
+    ///
+    /// ## Panic
+    ///
+    /// Panics if `pixels` is too small for the given dimensions.
+    pub fn present_pixels(&self, pixels: &[u8], width: u32, height: u32, format: PixelFormat,
+                          damage: Option<&[Rect]>)
+    {
+        assert!(pixels.len() >= width as usize * height as usize * 4,
+                "The pixel buffer is smaller than its dimensions");
+        self.window.present_pixels(pixels, width, height, format, damage)
+    }
+
+    /// Returns the text held by a selection, or `None` if it doesn't hold text.
+    pub fn get_clipboard_text(&self, selection: Selection) -> Option<String> {
+        TEXT_MIME_TYPES.iter()
+                       .filter_map(|mime_type| self.window.get_clipboard(selection, mime_type))
+                       .next()
+                       .map(|data| String::from_utf8_lossy(&data).into_owned())
+    }
+}
+
+// Panics if the pixels of the icon don't fill its dimensions.
+fn check_icon(image: &Icon) {
+    assert!(image.rgba.len() >= image.width as usize * image.height as usize * 4,
+            "The pixel buffer is smaller than the dimensions of the icon");
+}
+
+// Builds a `text/uri-list` out of a list of paths. Relative paths are taken from the current
+// directory, and skipped if it isn't available.
+fn file_uri_list(paths: &[PathBuf]) -> Vec<u8> {
+    let mut list = Vec::new();
+    for path in paths {
+        let path = if path.is_absolute() {
+            path.clone()
+        } else {
+            match env::current_dir() {
+                Ok(dir) => dir.join(path),
+                Err(_) => continue,
+            }
+        };
+
+        list.extend_from_slice(b"file://");
+        // everything except the unreserved characters and the separators is escaped
+        for &b in path_bytes(&path).iter() {
+            match b {
+                b'a' ..= b'z' | b'A' ..= b'Z' | b'0' ..= b'9' |
+                b'-' | b'_' | b'.' | b'~' | b'/' => list.push(b),
+                _ => list.extend_from_slice(format!("%{:02X}", b).as_bytes()),
+            }
+        }
+        list.extend_from_slice(b"\r\n");
+    }
+    list
+}
+
+// The bytes of a path, which don't have to be valid UTF-8 on Unix.
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Cow<[u8]> {
+    use std::os::unix::ffi::OsStrExt;
+    Cow::Borrowed(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Cow<[u8]> {
+    match path.to_string_lossy() {
+        Cow::Borrowed(path) => Cow::Borrowed(path.as_bytes()),
+        Cow::Owned(path) => Cow::Owned(path.into_bytes()),
+    }
 }
 
 /// Represents a thread safe subset of operations that can be called
@@ -478,3 +870,35 @@ impl MonitorId {
         id.get_dimensions()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::file_uri_list;
+
+    #[test]
+    fn uri_list_escapes_reserved_characters() {
+        let paths = vec![PathBuf::from("/tmp/a b%c#.txt"), PathBuf::from("/tmp/été")];
+        assert_eq!(file_uri_list(&paths), b"file:///tmp/a%20b%25c%23.txt\r\nfile:///tmp/%C3%A9t%C3%A9\r\n".to_vec());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn uri_list_keeps_non_utf8_bytes() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let paths = vec![PathBuf::from(OsStr::from_bytes(b"/tmp/\xff\xfe"))];
+        assert_eq!(file_uri_list(&paths), b"file:///tmp/%FF%FE\r\n".to_vec());
+    }
+
+    #[test]
+    fn uri_list_resolves_relative_paths() {
+        let expected = env::current_dir().unwrap().join("file.txt");
+        let list = file_uri_list(&[PathBuf::from("file.txt")]);
+        assert_eq!(list, file_uri_list(&[expected]));
+        assert!(list.starts_with(b"file:///"));
+    }
+}